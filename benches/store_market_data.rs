@@ -0,0 +1,90 @@
+//! Compares the per-row and the bulk write path of a market page.
+//! Needs the database from `configuration/`; every write is rolled back.
+//!
+//!     cargo bench --bench store_market_data
+use std::time::{Duration, Instant};
+
+use server::{
+    configuration::get_configuration,
+    domains::{Currency, MarketData},
+    routes::{store_market_data, store_market_page},
+    startup::get_connection_pool,
+};
+use sqlx::PgPool;
+
+const PAGE_SIZE: usize = 250;
+const PAGES: usize = 8;
+const SOURCE: &str = "bench";
+
+fn page(index: usize) -> Vec<MarketData> {
+    (0..PAGE_SIZE)
+        .map(|i| {
+            let rank = index * PAGE_SIZE + i + 1;
+            MarketData {
+                id: Some(format!("bench-coin-{}", rank)),
+                symbol: Some(format!("b{}", rank)),
+                name: Some(format!("Bench Coin {}", rank)),
+                current_price: Some(rank as f64),
+                market_cap: Some(1e9 / rank as f64),
+                market_cap_rank: Some(rank as i32),
+                total_volume: Some(1e6),
+                last_updated: Some("2023-01-01T00:00:00.000Z".into()),
+                ..MarketData::default()
+            }
+        })
+        .collect()
+}
+
+async fn per_row(pool: &PgPool, pages: &[Vec<MarketData>]) -> Duration {
+    let mut transaction = pool.begin().await.unwrap();
+    let started = Instant::now();
+    for page in pages {
+        for data in page {
+            store_market_data(&mut transaction, data, &Currency::USD, SOURCE)
+                .await
+                .unwrap();
+        }
+    }
+    let elapsed = started.elapsed();
+    transaction.rollback().await.unwrap();
+    elapsed
+}
+
+async fn bulk(pool: &PgPool, pages: &[Vec<MarketData>]) -> Duration {
+    let mut transaction = pool.begin().await.unwrap();
+    let started = Instant::now();
+    for page in pages {
+        let rows: Vec<&MarketData> = page.iter().collect();
+        store_market_page(&mut transaction, &rows, &Currency::USD, SOURCE)
+            .await
+            .unwrap();
+    }
+    let elapsed = started.elapsed();
+    transaction.rollback().await.unwrap();
+    elapsed
+}
+
+#[tokio::main]
+async fn main() {
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    let pool = get_connection_pool(&configuration.database);
+    let pages: Vec<Vec<MarketData>> = (0..PAGES).map(page).collect();
+    // Warm up the connection and the statement cache of both paths.
+    per_row(&pool, &pages[..1]).await;
+    bulk(&pool, &pages[..1]).await;
+
+    let per_row = per_row(&pool, &pages).await;
+    let bulk = bulk(&pool, &pages).await;
+    let rows = (PAGE_SIZE * PAGES) as f64;
+    println!("{} pages of {} rows", PAGES, PAGE_SIZE);
+    println!(
+        "per-row: {:>10.2?} ({:.0} rows/s)",
+        per_row,
+        rows / per_row.as_secs_f64()
+    );
+    println!(
+        "bulk:    {:>10.2?} ({:.0} rows/s)",
+        bulk,
+        rows / bulk.as_secs_f64()
+    );
+}
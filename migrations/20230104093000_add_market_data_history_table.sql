@@ -0,0 +1,15 @@
+-- Add migration script here
+
+CREATE TABLE
+    market_data_history (
+        id TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        current_price FLOAT,
+        market_cap FLOAT,
+        market_cap_rank INTEGER,
+        total_volume FLOAT,
+        price_change_percentage_24h FLOAT,
+        last_updated TEXT,
+        recorded_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (id, currency, recorded_at)
+    );
@@ -0,0 +1,7 @@
+-- Add migration script here
+
+ALTER TABLE market_data ADD COLUMN currency TEXT NOT NULL DEFAULT 'usd';
+ALTER TABLE market_data DROP CONSTRAINT market_data_pkey;
+ALTER TABLE market_data DROP CONSTRAINT IF EXISTS market_data_id_key;
+ALTER TABLE market_data ADD PRIMARY KEY (id, currency);
+CREATE INDEX market_data_symbol_currency_idx ON market_data (symbol, currency);
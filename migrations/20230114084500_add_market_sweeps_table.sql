@@ -0,0 +1,15 @@
+-- Add migration script here
+
+CREATE TABLE
+    market_sweeps (
+        id BIGSERIAL PRIMARY KEY,
+        next_page INTEGER NOT NULL DEFAULT 1,
+        pages_fetched INTEGER NOT NULL DEFAULT 0,
+        coins_stored INTEGER NOT NULL DEFAULT 0,
+        coins_skipped INTEGER NOT NULL DEFAULT 0,
+        started_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        finished_at timestamptz
+    );
+
+-- At most one sweep can be in progress at any time.
+CREATE UNIQUE INDEX market_sweeps_active_idx ON market_sweeps ((finished_at IS NULL)) WHERE finished_at IS NULL;
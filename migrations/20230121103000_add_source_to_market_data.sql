@@ -0,0 +1,9 @@
+-- Add migration script here
+
+ALTER TABLE market_data ADD COLUMN source TEXT NOT NULL DEFAULT 'coingecko';
+ALTER TABLE market_data DROP CONSTRAINT market_data_pkey;
+ALTER TABLE market_data ADD PRIMARY KEY (id, currency, source);
+
+ALTER TABLE market_data_history ADD COLUMN source TEXT NOT NULL DEFAULT 'coingecko';
+ALTER TABLE market_data_history DROP CONSTRAINT market_data_history_pkey;
+ALTER TABLE market_data_history ADD PRIMARY KEY (id, currency, source, recorded_at);
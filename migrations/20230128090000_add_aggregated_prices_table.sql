@@ -0,0 +1,15 @@
+-- Add migration script here
+
+CREATE TABLE
+    aggregated_prices (
+        id TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        price FLOAT NOT NULL,
+        min_price FLOAT NOT NULL,
+        max_price FLOAT NOT NULL,
+        spread_percentage FLOAT NOT NULL,
+        source_count INTEGER NOT NULL,
+        sources TEXT[] NOT NULL,
+        updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (id, currency)
+    );
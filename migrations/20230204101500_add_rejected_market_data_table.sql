@@ -0,0 +1,12 @@
+-- Add migration script here
+
+CREATE TABLE
+    rejected_market_data (
+        id BIGSERIAL PRIMARY KEY,
+        coin_id TEXT,
+        currency TEXT NOT NULL,
+        source TEXT NOT NULL,
+        payload JSONB NOT NULL,
+        error TEXT NOT NULL,
+        rejected_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
@@ -0,0 +1,15 @@
+-- Add migration script here
+
+CREATE TABLE
+    worker_schedule (
+        name TEXT PRIMARY KEY,
+        next_due_at timestamptz NOT NULL,
+        updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+
+-- Every refresh tier keeps its own sweep, so one sweep can be in progress per tier.
+-- A sweep left over from the single schedule is closed; the tiers start their own.
+UPDATE market_sweeps SET finished_at = CURRENT_TIMESTAMP WHERE finished_at IS NULL;
+ALTER TABLE market_sweeps ADD COLUMN tier TEXT NOT NULL DEFAULT 'all';
+DROP INDEX market_sweeps_active_idx;
+CREATE UNIQUE INDEX market_sweeps_active_idx ON market_sweeps (tier) WHERE finished_at IS NULL;
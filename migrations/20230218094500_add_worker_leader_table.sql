@@ -0,0 +1,11 @@
+-- Add migration script here
+
+-- Last instance that won the advisory lock `lock_id`. The lock itself lives in
+-- pg_locks; this row only tells which instance holds it.
+CREATE TABLE
+    worker_leader (
+        lock_id BIGINT PRIMARY KEY,
+        instance_id TEXT NOT NULL,
+        acquired_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        heartbeat_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
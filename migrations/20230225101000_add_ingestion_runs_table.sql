@@ -0,0 +1,20 @@
+-- Add migration script here
+
+-- One row per provider and currency fetched for a page of a sweep.
+CREATE TABLE
+    ingestion_runs (
+        id BIGSERIAL PRIMARY KEY,
+        sweep_id BIGINT NOT NULL REFERENCES market_sweeps (id),
+        tier TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        page INTEGER NOT NULL,
+        currency TEXT NOT NULL,
+        started_at timestamptz NOT NULL,
+        finished_at timestamptz NOT NULL,
+        rows_fetched INTEGER NOT NULL DEFAULT 0,
+        rows_stored INTEGER NOT NULL DEFAULT 0,
+        rows_rejected INTEGER NOT NULL DEFAULT 0,
+        error TEXT
+    );
+
+CREATE INDEX ingestion_runs_provider_idx ON ingestion_runs (provider, id);
@@ -0,0 +1,4 @@
+-- Add migration script here
+
+-- Hourly prices over the last 7 days, ending at `last_updated`.
+ALTER TABLE market_data ADD COLUMN sparkline_7d FLOAT8[];
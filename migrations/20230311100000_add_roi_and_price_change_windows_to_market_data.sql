@@ -0,0 +1,13 @@
+-- Add migration script here
+
+ALTER TABLE market_data
+    ADD COLUMN roi_times FLOAT8,
+    ADD COLUMN roi_currency TEXT,
+    ADD COLUMN roi_percentage FLOAT8,
+    ADD COLUMN price_change_percentage_1h_in_currency FLOAT8,
+    ADD COLUMN price_change_percentage_24h_in_currency FLOAT8,
+    ADD COLUMN price_change_percentage_7d_in_currency FLOAT8,
+    ADD COLUMN price_change_percentage_14d_in_currency FLOAT8,
+    ADD COLUMN price_change_percentage_30d_in_currency FLOAT8,
+    ADD COLUMN price_change_percentage_200d_in_currency FLOAT8,
+    ADD COLUMN price_change_percentage_1y_in_currency FLOAT8;
@@ -0,0 +1,31 @@
+-- Add migration script here
+
+CREATE TABLE
+    coins (
+        id TEXT PRIMARY KEY,
+        symbol TEXT NOT NULL,
+        name TEXT NOT NULL,
+        updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
+
+CREATE INDEX coins_symbol_idx ON coins (lower(symbol));
+
+-- Contract address of a coin on every chain it is deployed to, as listed by CoinGecko.
+CREATE TABLE
+    coin_platforms (
+        coin_id TEXT NOT NULL REFERENCES coins (id) ON DELETE CASCADE,
+        platform TEXT NOT NULL,
+        contract_address TEXT NOT NULL,
+        PRIMARY KEY (coin_id, platform)
+    );
+
+CREATE INDEX coin_platforms_address_idx ON coin_platforms (platform, lower(contract_address));
+
+-- Coins already stored become the first registry entries, until the registry is refreshed.
+INSERT INTO coins (id, symbol, name)
+SELECT DISTINCT ON (id) id, symbol, COALESCE(name, symbol)
+FROM market_data
+ORDER BY id, updated_at DESC;
+
+ALTER TABLE market_data
+    ADD CONSTRAINT market_data_coin_id_fkey FOREIGN KEY (id) REFERENCES coins (id);
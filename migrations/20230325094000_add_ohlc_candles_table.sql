@@ -0,0 +1,17 @@
+-- Add migration script here
+
+CREATE TABLE
+    ohlc_candles (
+        coin_id TEXT NOT NULL REFERENCES coins (id) ON DELETE CASCADE,
+        currency TEXT NOT NULL,
+        interval TEXT NOT NULL,
+        -- Candles are keyed by the end of the period they cover, as CoinGecko reports them.
+        close_time timestamptz NOT NULL,
+        open FLOAT8 NOT NULL,
+        high FLOAT8 NOT NULL,
+        low FLOAT8 NOT NULL,
+        close FLOAT8 NOT NULL,
+        source TEXT NOT NULL,
+        updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        PRIMARY KEY (coin_id, currency, interval, close_time)
+    );
@@ -0,0 +1,18 @@
+-- Add migration script here
+
+-- Progress of every backfill, so an interrupted run resumes from its cursor.
+-- Rerunning with a later end extends the same backfill.
+CREATE TABLE
+    backfill_progress (
+        coin_id TEXT NOT NULL,
+        currency TEXT NOT NULL,
+        range_from timestamptz NOT NULL,
+        range_to timestamptz NOT NULL,
+        -- Everything before the cursor has been written.
+        cursor timestamptz NOT NULL,
+        points_written BIGINT NOT NULL DEFAULT 0,
+        started_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+        finished_at timestamptz,
+        PRIMARY KEY (coin_id, currency, range_from)
+    );
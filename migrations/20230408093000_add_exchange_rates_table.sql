@@ -0,0 +1,13 @@
+-- Add migration script here
+
+-- What one BTC is worth in every currency, as last reported by the rates provider.
+CREATE TABLE
+    exchange_rates (
+        currency TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        unit TEXT NOT NULL,
+        rate_type TEXT NOT NULL,
+        value FLOAT8 NOT NULL,
+        source TEXT NOT NULL,
+        updated_at timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+    );
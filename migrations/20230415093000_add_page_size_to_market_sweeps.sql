@@ -0,0 +1,5 @@
+-- Add migration script here
+
+-- The cursor of a sweep counts pages of this size. Sweeps started before it was
+-- recorded are left without one, and are restarted rather than resumed.
+ALTER TABLE market_sweeps ADD COLUMN page_size INTEGER;
@@ -0,0 +1,5 @@
+-- Add migration script here
+
+-- Bumped every time an instance wins the lock. The leader checks it in every page
+-- transaction, so a page it is still writing after losing the lock is rolled back.
+ALTER TABLE worker_leader ADD COLUMN generation BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,9 @@
+-- Add migration script here
+
+-- Whether the sweep reached its last page, as opposed to being cut short by
+-- max_pages or restarted by a page size change.
+ALTER TABLE market_sweeps ADD COLUMN completed BOOLEAN NOT NULL DEFAULT false;
+
+-- Provider health only looks at the runs of a recent window.
+DROP INDEX ingestion_runs_provider_idx;
+CREATE INDEX ingestion_runs_provider_started_at_idx ON ingestion_runs (provider, started_at);
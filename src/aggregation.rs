@@ -0,0 +1,273 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{Postgres, Transaction};
+
+use crate::{
+    configuration::AggregationSetting,
+    domains::{Currency, Quote},
+};
+
+/// Consensus price of a coin across every source that reported a usable quote.
+#[derive(Debug, PartialEq)]
+pub struct AggregatedPrice {
+    pub price: f64,
+    pub min_price: f64,
+    pub max_price: f64,
+    /// `(max - min) / price` of the accepted quotes, in percent.
+    pub spread_percentage: f64,
+    pub sources: Vec<String>,
+}
+
+/// Median of the fresh quotes after dropping the ones further than
+/// `max_deviation_percentage` away from the median of all fresh quotes.
+pub fn aggregate(
+    quotes: &[Quote],
+    now: DateTime<Utc>,
+    max_age: Duration,
+    max_deviation_percentage: f64,
+) -> Option<AggregatedPrice> {
+    let fresh: Vec<&Quote> = quotes
+        .iter()
+        .filter(|q| q.price.is_finite() && q.price > 0.0)
+        .filter(|q| now - q.last_updated <= max_age)
+        .collect();
+    let reference = median(fresh.iter().map(|q| q.price).collect())?;
+    let accepted: Vec<&Quote> = fresh
+        .into_iter()
+        .filter(|q| (q.price - reference).abs() / reference * 100.0 <= max_deviation_percentage)
+        .collect();
+    let prices: Vec<f64> = accepted.iter().map(|q| q.price).collect();
+    let price = median(prices.clone())?;
+    let min_price = prices.iter().cloned().fold(f64::INFINITY, f64::min);
+    let max_price = prices.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    Some(AggregatedPrice {
+        price,
+        min_price,
+        max_price,
+        spread_percentage: (max_price - min_price) / price * 100.0,
+        sources: accepted.iter().map(|q| q.source.clone()).collect(),
+    })
+}
+
+fn median(mut values: Vec<f64>) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    if values.len().is_multiple_of(2) {
+        Some((values[mid - 1] + values[mid]) / 2.0)
+    } else {
+        Some(values[mid])
+    }
+}
+
+/// Aggregates the stored quotes of every source for `ids` and upserts the result.
+/// The aggregated price of a coin whose quotes were all rejected is deleted rather
+/// than left to look current. Returns how many coins got an aggregated price.
+pub async fn aggregate_stored_prices(
+    transaction: &mut Transaction<'_, Postgres>,
+    ids: &[String],
+    currency: &Currency,
+    settings: &AggregationSetting,
+) -> Result<usize, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"
+            SELECT id, source, current_price, total_volume, last_updated, updated_at
+            FROM market_data
+            WHERE currency = $1 AND id = ANY($2) AND current_price IS NOT NULL
+            ORDER BY id
+            "#,
+        currency.as_str(),
+        ids,
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    let now = Utc::now();
+    let mut aggregated = 0;
+    let mut unpriced: Vec<&str> = ids
+        .iter()
+        .filter(|id| !rows.iter().any(|row| &row.id == *id))
+        .map(String::as_str)
+        .collect();
+    for coin_rows in rows.chunk_by(|a, b| a.id == b.id) {
+        let quotes: Vec<Quote> = coin_rows
+            .iter()
+            .map(|row| Quote {
+                coin_id: row.id.clone(),
+                currency: *currency,
+                price: row.current_price.unwrap_or_default(),
+                volume_24h: row.total_volume,
+                last_updated: row
+                    .last_updated
+                    .as_deref()
+                    .and_then(|ts| DateTime::parse_from_rfc3339(ts).ok())
+                    .map(|ts| ts.with_timezone(&Utc))
+                    .unwrap_or(row.updated_at),
+                source: row.source.clone(),
+            })
+            .collect();
+        let Some(price) = aggregate(
+            &quotes,
+            now,
+            settings.max_quote_age(),
+            settings.max_deviation_percentage,
+        ) else {
+            unpriced.push(&coin_rows[0].id);
+            continue;
+        };
+        store_aggregated_price(transaction, &coin_rows[0].id, currency, &price).await?;
+        aggregated += 1;
+    }
+    if !unpriced.is_empty() {
+        delete_aggregated_prices(transaction, &unpriced, currency).await?;
+    }
+    Ok(aggregated)
+}
+
+async fn delete_aggregated_prices(
+    transaction: &mut Transaction<'_, Postgres>,
+    ids: &[&str],
+    currency: &Currency,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        "DELETE FROM aggregated_prices WHERE currency = $1 AND id = ANY($2)",
+        currency.as_str(),
+        ids as &[&str],
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+async fn store_aggregated_price(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: &str,
+    currency: &Currency,
+    price: &AggregatedPrice,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            INSERT INTO aggregated_prices (
+                id,
+                currency,
+                price,
+                min_price,
+                max_price,
+                spread_percentage,
+                source_count,
+                sources
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (id, currency) DO UPDATE SET
+                price = $3,
+                min_price = $4,
+                max_price = $5,
+                spread_percentage = $6,
+                source_count = $7,
+                sources = $8,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        id,
+        currency.as_str(),
+        price.price,
+        price.min_price,
+        price.max_price,
+        price.spread_percentage,
+        price.sources.len() as i32,
+        &price.sources,
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(source: &str, price: f64, age_seconds: i64, now: DateTime<Utc>) -> Quote {
+        Quote {
+            coin_id: "bitcoin".to_string(),
+            currency: Currency::USD,
+            price,
+            volume_24h: None,
+            last_updated: now - Duration::seconds(age_seconds),
+            source: source.to_string(),
+        }
+    }
+
+    #[test]
+    fn median_of_an_odd_count_is_the_middle_value() {
+        assert_eq!(median(vec![3.0, 1.0, 2.0]), Some(2.0));
+        assert_eq!(median(vec![7.0]), Some(7.0));
+    }
+
+    #[test]
+    fn median_of_an_even_count_is_the_mean_of_the_middle_values() {
+        assert_eq!(median(vec![4.0, 1.0, 3.0, 2.0]), Some(2.5));
+        assert_eq!(median(vec![1.0, 2.0]), Some(1.5));
+    }
+
+    #[test]
+    fn median_of_nothing_is_none() {
+        assert_eq!(median(vec![]), None);
+    }
+
+    #[test]
+    fn agreeing_quotes_are_all_accepted() {
+        let now = Utc::now();
+        let quotes = [quote("a", 100.0, 0, now), quote("b", 101.0, 0, now), quote("c", 99.0, 0, now)];
+
+        let price = aggregate(&quotes, now, Duration::minutes(5), 5.0).unwrap();
+
+        assert_eq!(price.price, 100.0);
+        assert_eq!(price.min_price, 99.0);
+        assert_eq!(price.max_price, 101.0);
+        assert_eq!(price.spread_percentage, 2.0);
+        assert_eq!(price.sources, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn outliers_are_rejected() {
+        let now = Utc::now();
+        let quotes = [
+            quote("a", 100.0, 0, now),
+            quote("b", 102.0, 0, now),
+            quote("c", 150.0, 0, now),
+            quote("d", 98.0, 0, now),
+        ];
+
+        let price = aggregate(&quotes, now, Duration::minutes(5), 5.0).unwrap();
+
+        assert_eq!(price.price, 100.0);
+        assert_eq!(price.max_price, 102.0);
+        assert_eq!(price.sources, ["a", "b", "d"]);
+    }
+
+    #[test]
+    fn stale_and_unusable_quotes_are_left_out() {
+        let now = Utc::now();
+        let quotes = [
+            quote("a", 100.0, 0, now),
+            quote("stale", 90.0, 600, now),
+            quote("zero", 0.0, 0, now),
+            quote("nan", f64::NAN, 0, now),
+        ];
+
+        let price = aggregate(&quotes, now, Duration::minutes(5), 5.0).unwrap();
+
+        assert_eq!(price.price, 100.0);
+        assert_eq!(price.sources, ["a"]);
+    }
+
+    #[test]
+    fn nothing_is_aggregated_when_every_quote_is_rejected() {
+        let now = Utc::now();
+        // Both quotes are 50% away from their median of 200.
+        let quotes = [quote("a", 100.0, 0, now), quote("b", 300.0, 0, now)];
+        assert_eq!(aggregate(&quotes, now, Duration::minutes(5), 5.0), None);
+
+        let stale = [quote("a", 100.0, 600, now)];
+        assert_eq!(aggregate(&stale, now, Duration::minutes(5), 5.0), None);
+        assert_eq!(aggregate(&[], now, Duration::minutes(5), 5.0), None);
+    }
+}
@@ -0,0 +1,279 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, NaiveDate, SecondsFormat, TimeZone, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+
+use crate::{
+    cli::BackfillArgs,
+    configuration::Settings,
+    domains::Currency,
+    gecko_client::{GeckoClient, MarketChart, MarketChartRangeRequest},
+    shutdown::Shutdown,
+    startup::get_connection_pool,
+};
+
+/// Longest range requested at once; CoinGecko turns ranges of 90 days or more into daily points.
+const CHUNK_DAYS: i64 = 89;
+/// Source the backfilled history is stored under.
+const SOURCE: &str = "coingecko";
+
+/// One point of `market_chart/range`, merged from its three series.
+#[derive(Default)]
+struct ChartPoint {
+    price: Option<f64>,
+    market_cap: Option<f64>,
+    total_volume: Option<f64>,
+}
+
+/// Fills `market_data_history` of every coin over the requested range, one chunk at a time.
+/// Points already stored are left untouched, and the progress is committed with every chunk,
+/// so an interrupted backfill resumes where it stopped when run again.
+pub async fn run_backfill(
+    configuration: Settings,
+    args: BackfillArgs,
+    shutdown: Shutdown,
+) -> Result<(), anyhow::Error> {
+    let currency = match args.vs {
+        Some(vs) => Currency::try_from(vs.to_lowercase()).map_err(anyhow::Error::msg)?,
+        None => configuration.worker.currencies().map_err(anyhow::Error::msg)?[0],
+    };
+    let from = start_of_day(args.from);
+    let to = args.to.map(start_of_day).unwrap_or_else(Utc::now);
+    if from >= to {
+        anyhow::bail!("--from must be earlier than --to");
+    }
+    let pool = get_connection_pool(&configuration.database);
+    let client = configuration.gecko_client.clone().client().map_err(anyhow::Error::msg)?;
+    let mut failed = vec![];
+    for id in &args.coins {
+        if shutdown.is_requested() {
+            break;
+        }
+        if let Err(e) = backfill_coin(&pool, &client, id, &currency, from, to, shutdown.clone()).await {
+            println!("Backfill of {} failed: {:?}", id, e);
+            failed.push(id.as_str());
+        }
+    }
+    pool.close().await;
+    if !failed.is_empty() {
+        anyhow::bail!("Backfill failed for {}", failed.join(", "));
+    }
+    Ok(())
+}
+
+async fn backfill_coin(
+    pool: &PgPool,
+    client: &GeckoClient,
+    id: &str,
+    currency: &Currency,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    mut shutdown: Shutdown,
+) -> Result<(), anyhow::Error> {
+    let (mut cursor, mut points_written) = resume_backfill(pool, id, currency, from, to).await?;
+    if cursor > from {
+        println!("Resuming the backfill of {} from {}", id, cursor);
+    }
+    while cursor < to {
+        let chunk_end = (cursor + Duration::days(CHUNK_DAYS)).min(to);
+        let request = MarketChartRangeRequest {
+            id: id.to_string(),
+            vs_currency: *currency,
+            from: cursor.timestamp(),
+            to: chunk_end.timestamp(),
+        };
+        // Stopping mid-chunk is safe: the chunk is fetched again on the next run.
+        let chart = tokio::select! {
+            chart = client.market_chart_range(&request) => chart?,
+            _ = shutdown.requested() => {
+                println!("Backfill of {} interrupted at {}", id, cursor);
+                return Ok(());
+            }
+        };
+        let points: BTreeMap<_, _> = merge_chart(chart)
+            .into_iter()
+            .filter(|(recorded_at, _)| *recorded_at >= cursor && *recorded_at < chunk_end)
+            .collect();
+        let mut transaction = pool.begin().await?;
+        let written = store_points(&mut transaction, id, currency, &points).await?;
+        advance_backfill(&mut transaction, id, currency, from, chunk_end, written).await?;
+        transaction.commit().await?;
+        cursor = chunk_end;
+        points_written += written;
+        let done = (cursor - from).num_seconds() * 100 / (to - from).num_seconds();
+        println!(
+            "Backfill of {} in {}: {} points written up to {} ({}%)",
+            id,
+            currency.as_str(),
+            points_written,
+            cursor,
+            done
+        );
+    }
+    finish_backfill(pool, id, currency, from).await?;
+    println!("Backfill of {} finished", id);
+    Ok(())
+}
+
+fn start_of_day(day: NaiveDate) -> DateTime<Utc> {
+    Utc.from_utc_datetime(&day.and_hms_opt(0, 0, 0).expect("Midnight is a valid time"))
+}
+
+/// Lines the price, market cap and volume series up by timestamp.
+fn merge_chart(chart: MarketChart) -> BTreeMap<DateTime<Utc>, ChartPoint> {
+    let mut points: BTreeMap<DateTime<Utc>, ChartPoint> = BTreeMap::new();
+    for value in chart.prices {
+        if let Some(point) = point_at(&mut points, value.0) {
+            point.price = value.1;
+        }
+    }
+    for value in chart.market_caps {
+        if let Some(point) = point_at(&mut points, value.0) {
+            point.market_cap = value.1;
+        }
+    }
+    for value in chart.total_volumes {
+        if let Some(point) = point_at(&mut points, value.0) {
+            point.total_volume = value.1;
+        }
+    }
+    points
+}
+
+fn point_at(points: &mut BTreeMap<DateTime<Utc>, ChartPoint>, timestamp: f64) -> Option<&mut ChartPoint> {
+    let recorded_at = Utc.timestamp_millis_opt(timestamp as i64).single()?;
+    Some(points.entry(recorded_at).or_default())
+}
+
+/// Inserts the points missing from the history and returns how many were.
+async fn store_points(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: &str,
+    currency: &Currency,
+    points: &BTreeMap<DateTime<Utc>, ChartPoint>,
+) -> Result<i64, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+            INSERT INTO market_data_history (
+                id, currency, source, recorded_at, current_price, market_cap, total_volume, last_updated
+            )
+            SELECT $1, $2, $3, *
+            FROM UNNEST($4::timestamptz[], $5::float8[], $6::float8[], $7::float8[], $8::text[])
+            ON CONFLICT (id, currency, source, recorded_at) DO NOTHING
+            "#,
+        id,
+        currency.as_str(),
+        SOURCE,
+        &points.keys().copied().collect::<Vec<_>>(),
+        &points.values().map(|p| p.price).collect::<Vec<_>>() as &[Option<f64>],
+        &points.values().map(|p| p.market_cap).collect::<Vec<_>>() as &[Option<f64>],
+        &points.values().map(|p| p.total_volume).collect::<Vec<_>>() as &[Option<f64>],
+        &points
+            .keys()
+            .map(|t| t.to_rfc3339_opts(SecondsFormat::Millis, true))
+            .collect::<Vec<_>>(),
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(result.rows_affected() as i64)
+}
+
+/// Registers the backfill, or extends an earlier one to `to`, and returns its cursor
+/// along with the points it has written so far.
+async fn resume_backfill(
+    pool: &PgPool,
+    id: &str,
+    currency: &Currency,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<(DateTime<Utc>, i64), sqlx::Error> {
+    let progress = sqlx::query!(
+        r#"
+            INSERT INTO backfill_progress (coin_id, currency, range_from, range_to, cursor)
+            VALUES ($1, $2, $3, $4, $3)
+            ON CONFLICT (coin_id, currency, range_from) DO UPDATE SET
+                range_to = EXCLUDED.range_to,
+                finished_at = NULL,
+                updated_at = CURRENT_TIMESTAMP
+            RETURNING cursor, points_written
+            "#,
+        id,
+        currency.as_str(),
+        from,
+        to,
+    )
+    .fetch_one(pool)
+    .await?;
+    Ok((progress.cursor, progress.points_written))
+}
+
+async fn advance_backfill(
+    transaction: &mut Transaction<'_, Postgres>,
+    id: &str,
+    currency: &Currency,
+    from: DateTime<Utc>,
+    cursor: DateTime<Utc>,
+    written: i64,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            UPDATE backfill_progress
+            SET cursor = $4, points_written = points_written + $5, updated_at = CURRENT_TIMESTAMP
+            WHERE coin_id = $1 AND currency = $2 AND range_from = $3
+            "#,
+        id,
+        currency.as_str(),
+        from,
+        cursor,
+        written,
+    )
+    .execute(&mut *transaction)
+    .await?;
+    Ok(())
+}
+
+async fn finish_backfill(
+    pool: &PgPool,
+    id: &str,
+    currency: &Currency,
+    from: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            UPDATE backfill_progress
+            SET finished_at = CURRENT_TIMESTAMP
+            WHERE coin_id = $1 AND currency = $2 AND range_from = $3
+            "#,
+        id,
+        currency.as_str(),
+        from,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gecko_client::ChartPoint as SeriesValue;
+
+    #[test]
+    fn merge_chart_lines_the_series_up_by_timestamp() {
+        let hour = 3_600_000.0;
+        let chart = MarketChart {
+            prices: vec![SeriesValue(0.0, Some(1.0)), SeriesValue(hour, Some(2.0))],
+            market_caps: vec![SeriesValue(0.0, Some(10.0)), SeriesValue(hour, None)],
+            // A volume without a price still makes a point.
+            total_volumes: vec![SeriesValue(hour, Some(5.0)), SeriesValue(2.0 * hour, Some(6.0))],
+        };
+
+        let points = merge_chart(chart);
+
+        let at = |millis: f64| &points[&Utc.timestamp_millis_opt(millis as i64).unwrap()];
+        assert_eq!(points.len(), 3);
+        assert_eq!((at(0.0).price, at(0.0).market_cap, at(0.0).total_volume), (Some(1.0), Some(10.0), None));
+        assert_eq!((at(hour).price, at(hour).market_cap, at(hour).total_volume), (Some(2.0), None, Some(5.0)));
+        assert_eq!((at(2.0 * hour).price, at(2.0 * hour).total_volume), (None, Some(6.0)));
+    }
+}
@@ -0,0 +1,139 @@
+use reqwest::{Client, StatusCode};
+use serde_aux::field_attributes::deserialize_number_from_string;
+use std::time::Duration;
+
+use crate::{
+    resilience::{retry_after, CircuitBreaker},
+    utils::error_chain_fmt,
+};
+
+/// How long calls are suspended after a rate limit that comes without a `Retry-After`.
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(60);
+
+pub struct BinanceClient {
+    http_client : Client,
+    url: String,
+    circuit_breaker: CircuitBreaker,
+}
+
+#[derive(thiserror::Error)]
+pub enum BinanceError {
+    #[error("Rate limited by Binance")]
+    RateLimited(Option<Duration>),
+    #[error("Binance calls are suspended after repeated failures")]
+    CircuitOpen,
+    #[error("Failed to send request to Binance")]
+    RequestError(#[from] reqwest::Error),
+}
+
+impl std::fmt::Debug for BinanceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct ExchangeInfo {
+    pub symbols: Vec<SymbolInfo>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub status: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ticker24h {
+    pub symbol: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub price_change: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub price_change_percent: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub last_price: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub high_price: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub low_price: f64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub quote_volume: f64,
+    pub close_time: i64,
+}
+
+impl BinanceClient {
+    pub fn new(
+        url:String,
+        timeout: Duration,
+        circuit_breaker: CircuitBreaker,
+    )->Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+        Self {
+            http_client,
+            url,
+            circuit_breaker,
+        }
+    }
+
+    /// Time left before Binance is called again, if the circuit breaker is open.
+    pub fn suspended_for(&self) -> Option<Duration> {
+        self.circuit_breaker.open_for()
+    }
+
+    pub async fn get_request(&self, request: &str, query: &[(&str, String)]) -> Result<reqwest::Response, BinanceError> {
+        if !self.circuit_breaker.allow() {
+            return Err(BinanceError::CircuitOpen);
+        }
+        let url = format!("{}/{}", self.url, request);
+        let response = self.http_client
+        .get(&url)
+        .query(query)
+        .send()
+        .await;
+        // 418 is the IP ban Binance escalates to when 429s are ignored; either way
+        // calls are suspended for as long as it asks, so the worker falls back meanwhile.
+        if let Ok(response) = &response {
+            if matches!(response.status(), StatusCode::TOO_MANY_REQUESTS | StatusCode::IM_A_TEAPOT) {
+                let retry_after = retry_after(response.headers());
+                self.circuit_breaker.trip(retry_after.unwrap_or(RATE_LIMIT_BACKOFF));
+                return Err(BinanceError::RateLimited(retry_after));
+            }
+        }
+        match response.and_then(|response| response.error_for_status()) {
+            Ok(response) => {
+                self.circuit_breaker.record_success();
+                Ok(response)
+            }
+            Err(e) => {
+                let client_error = e.status().is_some_and(|s| s.is_client_error());
+                if client_error {
+                    // Binance answered, so a trial call of the half-open circuit succeeded.
+                    self.circuit_breaker.record_success();
+                } else {
+                    self.circuit_breaker.record_failure();
+                }
+                Err(e.into())
+            }
+        }
+    }
+
+    pub async fn exchange_info(&self) -> Result<ExchangeInfo, BinanceError> {
+        Ok(self.get_request("exchangeInfo", &[])
+            .await?
+            .json::<ExchangeInfo>()
+            .await?)
+    }
+
+    /// 24 hour rolling statistics for the given trading pairs, e.g. `BTCUSDT`.
+    pub async fn ticker_24h(&self, symbols: &[String]) -> Result<Vec<Ticker24h>, BinanceError> {
+        let symbols = serde_json::to_string(symbols).expect("Failed to serialize symbols");
+        Ok(self.get_request("ticker/24hr", &[("symbols", symbols)])
+            .await?
+            .json::<Vec<Ticker24h>>()
+            .await?)
+    }
+}
@@ -0,0 +1,44 @@
+use chrono::NaiveDate;
+use clap::{Args, Parser, Subcommand};
+
+#[derive(Parser)]
+#[command(about = "Market data API and ingestion worker")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Serve the HTTP API only.
+    Serve,
+    /// Run the ingestion worker only, with a health endpoint.
+    Worker,
+    /// Serve the API and run the worker in the same process (default).
+    All,
+    /// Fill the price history of coins from CoinGecko, then exit.
+    Backfill(BackfillArgs),
+}
+
+/// Rerunning a backfill with the same arguments resumes it where it stopped.
+#[derive(Args, Clone, PartialEq, Eq)]
+pub struct BackfillArgs {
+    /// CoinGecko ids of the coins, comma separated.
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub coins: Vec<String>,
+    /// First day of the range.
+    #[arg(long)]
+    pub from: NaiveDate,
+    /// Day the range stops at, excluded; defaults to now.
+    #[arg(long)]
+    pub to: Option<NaiveDate>,
+    /// Currency of the history; defaults to the first worker currency.
+    #[arg(long)]
+    pub vs: Option<String>,
+}
+
+impl Cli {
+    pub fn command(self) -> Command {
+        self.command.unwrap_or(Command::All)
+    }
+}
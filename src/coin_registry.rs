@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+
+use sqlx::PgPool;
+
+use crate::domains::{Coin, ContractAddress};
+
+/// Upserts the registry and replaces the contract addresses of every listed coin.
+/// Coins missing from the list are kept, since stored market data refers to them.
+pub async fn store_coins(pool: &PgPool, coins: &[Coin]) -> Result<(), sqlx::Error> {
+    let mut seen = HashSet::new();
+    let coins: Vec<&Coin> = coins.iter().filter(|c| seen.insert(c.id.as_str())).collect();
+    let ids: Vec<String> = coins.iter().map(|c| c.id.clone()).collect();
+    let platforms: Vec<(String, String, String)> = coins
+        .iter()
+        .flat_map(|c| {
+            c.platforms
+                .iter()
+                .map(|(platform, address)| (c.id.clone(), platform.clone(), address.clone()))
+        })
+        .collect();
+    let mut transaction = pool.begin().await?;
+    sqlx::query!(
+        r#"
+            INSERT INTO coins (id, symbol, name)
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[])
+            ON CONFLICT (id) DO UPDATE SET
+                symbol = EXCLUDED.symbol,
+                name = EXCLUDED.name,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        &ids,
+        &coins.iter().map(|c| c.symbol.clone()).collect::<Vec<_>>(),
+        &coins.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+    )
+    .execute(&mut transaction)
+    .await?;
+    sqlx::query!(r#"DELETE FROM coin_platforms WHERE coin_id = ANY($1)"#, &ids)
+        .execute(&mut transaction)
+        .await?;
+    sqlx::query!(
+        r#"
+            INSERT INTO coin_platforms (coin_id, platform, contract_address)
+            SELECT * FROM UNNEST($1::text[], $2::text[], $3::text[])
+            "#,
+        &platforms.iter().map(|p| p.0.clone()).collect::<Vec<_>>(),
+        &platforms.iter().map(|p| p.1.clone()).collect::<Vec<_>>(),
+        &platforms.iter().map(|p| p.2.clone()).collect::<Vec<_>>(),
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await
+}
+
+/// A registered contract and the coin it belongs to.
+pub struct RegisteredContract {
+    pub coin_id: String,
+    pub symbol: String,
+    pub name: String,
+    pub contract_address: String,
+}
+
+/// Whether any registered coin is deployed to `platform`.
+pub async fn platform_exists(pool: &PgPool, platform: &str) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar!(
+        r#"SELECT EXISTS (SELECT 1 FROM coin_platforms WHERE platform = $1) AS "exists!""#,
+        platform,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Looks a contract up on `platform`. EVM addresses are matched regardless of case,
+/// others exactly, since encodings such as base58 are case-sensitive.
+pub async fn find_contract(
+    pool: &PgPool,
+    platform: &str,
+    address: &ContractAddress,
+) -> Result<Option<RegisteredContract>, sqlx::Error> {
+    sqlx::query_as!(
+        RegisteredContract,
+        r#"
+            SELECT c.id AS coin_id, c.symbol, c.name, p.contract_address
+            FROM coin_platforms p
+            JOIN coins c ON c.id = p.coin_id
+            WHERE p.platform = $1
+                AND lower(p.contract_address) = lower($2)
+                AND ($3 OR p.contract_address = $2)
+            ORDER BY c.id
+            LIMIT 1
+            "#,
+        platform,
+        address.as_str(),
+        address.is_evm(),
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn coin(id: &str, platform: &str, address: &str) -> Coin {
+        Coin {
+            id: id.to_string(),
+            symbol: id.to_string(),
+            name: id.to_string(),
+            platforms: HashMap::from([(platform.to_string(), address.to_string())]),
+        }
+    }
+
+    #[sqlx::test]
+    async fn evm_contracts_are_found_regardless_of_case(pool: PgPool) {
+        store_coins(&pool, &[coin("usd-coin", "ethereum", "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48")])
+            .await
+            .unwrap();
+        let address = ContractAddress::parse("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48").unwrap();
+
+        let contract = find_contract(&pool, "ethereum", &address).await.unwrap().unwrap();
+
+        assert_eq!(contract.coin_id, "usd-coin");
+        assert!(platform_exists(&pool, "ethereum").await.unwrap());
+        assert!(!platform_exists(&pool, "unknown-chain").await.unwrap());
+    }
+
+    #[sqlx::test]
+    async fn other_contracts_are_only_found_exactly(pool: PgPool) {
+        store_coins(&pool, &[coin("usd-coin", "solana", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")])
+            .await
+            .unwrap();
+        let exact = ContractAddress::parse("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v").unwrap();
+        let other_case = ContractAddress::parse("epjfwdd5aufqssqem2qn1xzybapc8g4weggkzwytdt1v").unwrap();
+
+        assert!(find_contract(&pool, "solana", &exact).await.unwrap().is_some());
+        assert!(find_contract(&pool, "solana", &other_case).await.unwrap().is_none());
+    }
+}
@@ -2,7 +2,16 @@ use serde_aux::field_attributes::deserialize_number_from_string;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{postgres::{PgConnectOptions, PgSslMode}};
 
-use crate::gecko_client::GeckoClient;
+use std::collections::HashMap;
+
+use crate::{
+    binance_client::BinanceClient,
+    domains::{CandleInterval, Currency},
+    gecko_client::{ApiPlan, GeckoApiKey, GeckoClient, MarketOrder},
+    live_token_prices::LiveTokenPrices,
+    price_source::BinanceSource,
+    resilience::{Backoff, CircuitBreaker, TokenBucket},
+};
 
 pub enum Environment {
     Local,
@@ -34,7 +43,28 @@ impl TryFrom<String> for Environment {
 pub struct Settings {
     pub application: ApplicationSetting,
     pub gecko_client: GeckoClientSetting,
+    pub binance_client: BinanceClientSetting,
     pub database: DatabaseSetting,
+    pub worker: WorkerSetting,
+    pub aggregation: AggregationSetting,
+    pub token_price: TokenPriceSetting,
+}
+
+impl Settings {
+    /// Checks every section, then the settings that depend on one another.
+    pub fn validate(&self) -> Result<(), String> {
+        self.gecko_client.validate()?;
+        self.worker.validate()?;
+        if !self.gecko_client.has_paid_plan() {
+            if let Some(interval) = self.worker.ohlc.intervals.iter().find(|i| i.requires_paid_plan()) {
+                return Err(format!(
+                    "worker.ohlc.intervals: {} candles need a Pro plan key in gecko_client",
+                    interval.as_str()
+                ));
+            }
+        }
+        Ok(())
+    }
 }
 
 #[derive(serde::Deserialize,Clone)]
@@ -42,9 +72,13 @@ pub struct ApplicationSetting {
     pub host: String,
     pub port: u16,
     pub base_url: String,
+    pub shutdown_timeout_seconds: u64,
 }
 
 impl ApplicationSetting {
+    pub fn shutdown_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.shutdown_timeout_seconds)
+    }
     pub fn url(&self) -> String {
         format!("{}:{}", self.host, self.port)
     }
@@ -53,19 +87,345 @@ impl ApplicationSetting {
 #[derive(serde::Deserialize,Clone)]
 pub struct GeckoClientSetting {
     pub url: String,
+    /// Used instead of `url` when a Pro plan API key is configured.
+    pub pro_url: String,
+    pub api_key: Option<Secret<String>>,
+    pub api_plan: ApiPlan,
     pub timeout_milliseconds: u64,
+    pub requests_per_minute: u32,
+    pub burst: u32,
+    pub max_retries: u32,
+    pub backoff_base_milliseconds: u64,
+    pub backoff_max_milliseconds: u64,
+    pub circuit_breaker_threshold: u32,
+    pub circuit_breaker_cooldown_seconds: u64,
 }
 
 impl GeckoClientSetting {
-    pub fn client(self)-> GeckoClient {
+    /// Fails on a key that cannot be sent in a header, which `validate` reports at startup.
+    pub fn client(self) -> Result<GeckoClient, String> {
         let timeout = self.timeout();
-        GeckoClient::new(self.url, timeout)
+        let url = self.base_url().to_string();
+        let api_key = self.api_key()?;
+        Ok(GeckoClient::new(
+            url,
+            timeout,
+            api_key,
+            TokenBucket::new(self.burst, self.requests_per_minute),
+            Backoff {
+                base: std::time::Duration::from_millis(self.backoff_base_milliseconds),
+                max: std::time::Duration::from_millis(self.backoff_max_milliseconds),
+                max_retries: self.max_retries,
+            },
+            CircuitBreaker::new(
+                self.circuit_breaker_threshold,
+                std::time::Duration::from_secs(self.circuit_breaker_cooldown_seconds),
+            ),
+        ))
+    }
+    pub fn validate(&self) -> Result<(), String> {
+        self.api_key().map(|_| ())
+    }
+    /// The configured key, if any; an empty key counts as none.
+    pub fn api_key(&self) -> Result<Option<GeckoApiKey>, String> {
+        self.api_key
+            .as_ref()
+            .filter(|key| !key.expose_secret().is_empty())
+            .map(|key| GeckoApiKey::new(self.api_plan, key))
+            .transpose()
+    }
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_millis(self.timeout_milliseconds)
+    }
+    /// Whether requests go out with a Pro plan key, the only paid plan.
+    pub fn has_paid_plan(&self) -> bool {
+        let has_key = self
+            .api_key
+            .as_ref()
+            .is_some_and(|key| !key.expose_secret().is_empty());
+        matches!(self.api_plan, ApiPlan::Pro) && has_key
+    }
+    pub fn base_url(&self) -> &str {
+        if self.has_paid_plan() {
+            &self.pro_url
+        } else {
+            &self.url
+        }
+    }
+}
+
+/// Live lookups of the token price endpoint, for tokens the worker has not stored.
+#[derive(serde::Deserialize,Clone)]
+pub struct TokenPriceSetting {
+    /// How long an answer is served from memory, unknown tokens included.
+    pub cache_ttl_seconds: u64,
+    /// Budget of the lookups, apart from the one the worker shares with them.
+    pub live_lookups_per_minute: u32,
+    pub live_lookup_burst: u32,
+}
+
+impl TokenPriceSetting {
+    pub fn live_prices(&self) -> LiveTokenPrices {
+        LiveTokenPrices::new(
+            std::time::Duration::from_secs(self.cache_ttl_seconds),
+            TokenBucket::new(self.live_lookup_burst, self.live_lookups_per_minute),
+        )
+    }
+}
+
+#[derive(serde::Deserialize,Clone)]
+pub struct BinanceClientSetting {
+    pub url: String,
+    pub timeout_milliseconds: u64,
+    pub circuit_breaker_threshold: u32,
+    pub circuit_breaker_cooldown_seconds: u64,
+    /// Binance base asset to the coin id its quotes are stored under.
+    pub assets: HashMap<String, String>,
+}
+
+impl BinanceClientSetting {
+    pub fn client(&self) -> BinanceClient {
+        BinanceClient::new(
+            self.url.clone(),
+            self.timeout(),
+            CircuitBreaker::new(
+                self.circuit_breaker_threshold,
+                std::time::Duration::from_secs(self.circuit_breaker_cooldown_seconds),
+            ),
+        )
+    }
+    pub fn source(self) -> BinanceSource {
+        BinanceSource::new(self.client(), self.assets)
     }
     pub fn timeout(&self) -> std::time::Duration {
         std::time::Duration::from_millis(self.timeout_milliseconds)
     }
 }
 
+#[derive(serde::Deserialize,Clone)]
+pub struct WorkerSetting {
+    /// When false the worker stays idle and only the API is served.
+    pub enabled: bool,
+    /// Pause between two pages of a sweep.
+    pub page_delay_seconds: u64,
+    /// Pause before retrying after a failed page.
+    pub error_delay_seconds: u64,
+    /// The worker reports itself unhealthy once the loop has made no progress for
+    /// this long, beyond the pause it is in.
+    pub stall_timeout_seconds: u64,
+    /// Coins per page, at most 250.
+    pub page_size: u16,
+    /// Order of the listing; tier ranks are positions in this order.
+    pub order: MarketOrder,
+    /// Pages fetched at most by a single sweep, unlimited when left out.
+    pub max_pages: Option<u32>,
+    /// Also ingest the 7 day sparkline of every coin.
+    pub sparkline: bool,
+    /// How often the coin registry is refreshed from `coins/list`.
+    pub coins_refresh_hours: u32,
+    /// How often the BTC exchange rates are refreshed. Prices in currencies that are
+    /// not swept are derived from the USD price with these rates.
+    pub exchange_rates_refresh_seconds: u64,
+    /// Ingestion runs are deleted once they are this old.
+    pub ingestion_runs_retention_hours: u32,
+    /// Must include `usd`, which the other currencies are derived from when not swept.
+    /// The first currency is also the default of the API.
+    pub currencies: Vec<String>,
+    /// Price sources in order of preference. The first available one serves the
+    /// sweep, the others are stored next to it.
+    pub providers: Vec<String>,
+    /// Refresh tiers in order of priority.
+    pub tiers: Vec<TierSetting>,
+    pub ohlc: OhlcSetting,
+    pub leader_election: LeaderElectionSetting,
+}
+
+/// OHLC candles of `coins` in every worker currency, at each of `intervals`.
+/// Each interval is refreshed once per candle period.
+#[derive(serde::Deserialize,Clone)]
+pub struct OhlcSetting {
+    /// CoinGecko ids; candles are not ingested when empty.
+    pub coins: Vec<String>,
+    pub intervals: Vec<CandleInterval>,
+}
+
+impl OhlcSetting {
+    /// Name of the interval's refresh in `worker_schedule`.
+    pub fn schedule_name(interval: CandleInterval) -> String {
+        format!("{}{}", crate::worker_schedule::OHLC_PREFIX, interval.as_str())
+    }
+}
+
+/// Only the instance holding the advisory lock `lock_id` runs the ingestion loop.
+#[derive(serde::Deserialize,Clone)]
+pub struct LeaderElectionSetting {
+    /// Identifies this instance in `worker_leader`; defaults to the host name.
+    pub instance_id: Option<String>,
+    pub lock_id: i64,
+    pub heartbeat_seconds: u64,
+    /// How often a follower tries to take the lock.
+    pub retry_seconds: u64,
+}
+
+impl LeaderElectionSetting {
+    pub fn instance_id(&self) -> String {
+        self.instance_id
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .unwrap_or_else(|| format!("instance-{}", std::process::id()))
+    }
+    pub fn heartbeat_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.heartbeat_seconds)
+    }
+    pub fn retry_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.retry_seconds)
+    }
+}
+
+/// Coins ranked `min_rank..=max_rank` by market cap, refreshed every `interval_seconds`.
+/// Without `max_rank` the tier runs until the listing is exhausted.
+#[derive(serde::Deserialize,Clone)]
+pub struct TierSetting {
+    pub name: String,
+    pub min_rank: u32,
+    pub max_rank: Option<u32>,
+    pub interval_seconds: u64,
+}
+
+impl TierSetting {
+    pub fn interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.interval_seconds as i64)
+    }
+    /// First page holding a coin of the tier.
+    pub fn first_page(&self, page_size: u16) -> i32 {
+        (self.min_rank.saturating_sub(1) / u32::from(page_size)) as i32 + 1
+    }
+    /// Last page holding a coin of the tier, if the tier is bounded.
+    pub fn last_page(&self, page_size: u16) -> Option<i32> {
+        self.max_rank
+            .map(|max_rank| (max_rank.saturating_sub(1) / u32::from(page_size)) as i32 + 1)
+    }
+    /// Name of the tier in `worker_schedule`.
+    pub fn schedule_name(&self) -> String {
+        format!("{}{}", crate::worker_schedule::TIER_PREFIX, self.name)
+    }
+}
+
+impl WorkerSetting {
+    /// Checks the settings up front so a bad value fails at startup instead of in the loop.
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.currencies()?.contains(&Currency::USD) {
+            return Err("worker.currencies must include usd, which the other currencies are derived from".into());
+        }
+        if self.page_size == 0 || self.page_size > 250 {
+            return Err(format!(
+                "worker.page_size must be between 1 and 250, got {}",
+                self.page_size
+            ));
+        }
+        if self.coins_refresh_hours == 0 {
+            return Err("worker.coins_refresh_hours must be at least 1".into());
+        }
+        if self.exchange_rates_refresh_seconds == 0 {
+            return Err("worker.exchange_rates_refresh_seconds must be at least 1".into());
+        }
+        if self.ingestion_runs_retention_hours == 0 {
+            return Err("worker.ingestion_runs_retention_hours must be at least 1".into());
+        }
+        if self.max_pages == Some(0) {
+            return Err("worker.max_pages must be at least 1 when set".into());
+        }
+        if self.providers.is_empty() {
+            return Err("worker.providers must contain at least one provider".into());
+        }
+        if let Some(provider) = self
+            .providers
+            .iter()
+            .find(|p| !["coingecko", "binance"].contains(&p.as_str()))
+        {
+            return Err(format!(
+                "worker.providers: unknown provider {}, expected coingecko or binance",
+                provider
+            ));
+        }
+        if self.tiers.is_empty() {
+            return Err("worker.tiers must contain at least one tier".into());
+        }
+        for (i, tier) in self.tiers.iter().enumerate() {
+            // Tiers share their schedule row and sweep cursor by name.
+            if self.tiers[..i].iter().any(|other| other.name == tier.name) {
+                return Err(format!("worker.tiers: the name {} is used by more than one tier", tier.name));
+            }
+            if tier.min_rank == 0 {
+                return Err(format!("worker.tiers.{}: min_rank starts at 1", tier.name));
+            }
+            if tier.max_rank.is_some_and(|max_rank| max_rank < tier.min_rank) {
+                return Err(format!(
+                    "worker.tiers.{}: max_rank must not be lower than min_rank",
+                    tier.name
+                ));
+            }
+            if tier.interval_seconds == 0 {
+                return Err(format!("worker.tiers.{}: interval_seconds must be at least 1", tier.name));
+            }
+        }
+        if !self.ohlc.coins.is_empty() && self.ohlc.intervals.is_empty() {
+            return Err("worker.ohlc.intervals must contain at least one interval when coins are set".into());
+        }
+        if self.stall_timeout_seconds == 0 {
+            return Err("worker.stall_timeout_seconds must be at least 1".into());
+        }
+        if self.leader_election.heartbeat_seconds == 0 || self.leader_election.retry_seconds == 0 {
+            return Err("worker.leader_election: heartbeat_seconds and retry_seconds must be at least 1".into());
+        }
+        Ok(())
+    }
+    pub fn page_delay(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.page_delay_seconds)
+    }
+    pub fn error_delay(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.error_delay_seconds)
+    }
+    pub fn stall_timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.stall_timeout_seconds)
+    }
+    pub fn coins_refresh_interval(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.coins_refresh_hours as i64)
+    }
+    pub fn exchange_rates_refresh_interval(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.exchange_rates_refresh_seconds as i64)
+    }
+    pub fn ingestion_runs_retention(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.ingestion_runs_retention_hours as i64)
+    }
+    pub fn currencies(&self) -> Result<Vec<Currency>, String> {
+        if self.currencies.is_empty() {
+            return Err("worker.currencies must contain at least one currency".into());
+        }
+        self.currencies
+            .iter()
+            .map(|c| {
+                Currency::try_from(c.to_lowercase())
+                    .map_err(|e| format!("worker.currencies: {}", e))
+            })
+            .collect()
+    }
+}
+
+#[derive(serde::Deserialize,Clone)]
+pub struct AggregationSetting {
+    /// Quotes older than this are left out of the aggregate.
+    pub max_quote_age_seconds: i64,
+    /// Quotes further than this from the median, in percent, are rejected as outliers.
+    pub max_deviation_percentage: f64,
+}
+
+impl AggregationSetting {
+    pub fn max_quote_age(&self) -> chrono::Duration {
+        chrono::Duration::seconds(self.max_quote_age_seconds)
+    }
+}
+
 #[derive(serde::Deserialize,Clone)]
 pub struct DatabaseSetting {
     pub host: String,
@@ -115,9 +475,67 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
     .add_source(
         config::Environment::with_prefix("APP")
             .prefix_separator("_")
-            .separator("__"),
+            .separator("__")
+            .try_parsing(true)
+            .list_separator(",")
+            .with_list_parse_key("worker.currencies")
+            .with_list_parse_key("worker.providers")
+            .with_list_parse_key("worker.ohlc.coins")
+            .with_list_parse_key("worker.ohlc.intervals"),
     )
     .build()?;
-    settings.try_deserialize::<Settings>()
+    let settings = settings.try_deserialize::<Settings>()?;
+    settings.validate().map_err(config::ConfigError::Message)?;
+    Ok(settings)
+
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(plan: ApiPlan, api_key: Option<&str>, intervals: Vec<CandleInterval>) -> Settings {
+        let mut settings = get_configuration().expect("Failed to read configuration.");
+        settings.gecko_client.api_plan = plan;
+        settings.gecko_client.api_key = api_key.map(|key| Secret::new(key.to_string()));
+        settings.worker.ohlc.intervals = intervals;
+        settings
+    }
+
+    #[test]
+    fn free_intervals_are_accepted_without_a_key() {
+        let intervals = vec![CandleInterval::M30, CandleInterval::H4, CandleInterval::D4];
+        assert!(settings(ApiPlan::Demo, None, intervals).validate().is_ok());
+    }
+
+    #[test]
+    fn paid_intervals_need_a_pro_plan_key() {
+        for interval in [CandleInterval::H1, CandleInterval::D1] {
+            assert!(settings(ApiPlan::Pro, None, vec![interval]).validate().is_err());
+            assert!(settings(ApiPlan::Demo, Some("key"), vec![interval]).validate().is_err());
+            assert!(settings(ApiPlan::Pro, Some(""), vec![interval]).validate().is_err());
+            assert!(settings(ApiPlan::Pro, Some("key"), vec![interval]).validate().is_ok());
+        }
+    }
+
+    #[test]
+    fn tier_names_must_be_unique() {
+        let mut settings = get_configuration().expect("Failed to read configuration.");
+        let mut duplicate = settings.worker.tiers[0].clone();
+        duplicate.min_rank = 5001;
+        duplicate.max_rank = None;
+        settings.worker.tiers.push(duplicate);
+        let error = settings.worker.validate().unwrap_err();
+        assert!(error.contains(&settings.worker.tiers[0].name), "{}", error);
+        settings.worker.tiers.last_mut().unwrap().name = "deep".to_string();
+        assert!(settings.worker.validate().is_ok());
+    }
 
-}
\ No newline at end of file
+    #[test]
+    fn usd_must_be_swept() {
+        let mut settings = get_configuration().expect("Failed to read configuration.");
+        settings.worker.currencies = vec!["eur".to_string()];
+        assert!(settings.worker.validate().unwrap_err().contains("usd"));
+        settings.worker.currencies = vec!["eur".to_string(), "usd".to_string()];
+        assert!(settings.worker.validate().is_ok());
+    }
+}
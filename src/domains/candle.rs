@@ -0,0 +1,72 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Granularity of OHLC candles.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum CandleInterval {
+    #[serde(rename = "30m")]
+    M30,
+    #[serde(rename = "1h")]
+    H1,
+    #[serde(rename = "4h")]
+    H4,
+    #[serde(rename = "1d")]
+    D1,
+    #[serde(rename = "4d")]
+    D4,
+}
+
+impl CandleInterval {
+    pub const ALL: [CandleInterval; 5] = [Self::M30, Self::H1, Self::H4, Self::D1, Self::D4];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::M30 => "30m",
+            Self::H1 => "1h",
+            Self::H4 => "4h",
+            Self::D1 => "1d",
+            Self::D4 => "4d",
+        }
+    }
+
+    pub fn duration(&self) -> Duration {
+        match self {
+            Self::M30 => Duration::minutes(30),
+            Self::H1 => Duration::hours(1),
+            Self::H4 => Duration::hours(4),
+            Self::D1 => Duration::days(1),
+            Self::D4 => Duration::days(4),
+        }
+    }
+
+    /// Hourly and daily candles are only served to paid CoinGecko plans.
+    pub fn requires_paid_plan(&self) -> bool {
+        matches!(self, Self::H1 | Self::D1)
+    }
+}
+
+impl TryFrom<String> for CandleInterval {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::ALL
+            .into_iter()
+            .find(|interval| interval.as_str() == value)
+            .ok_or_else(|| {
+                format!(
+                    "{} is not a supported interval, expected one of {}",
+                    value,
+                    Self::ALL.map(|i| i.as_str()).join(", ")
+                )
+            })
+    }
+}
+
+/// One OHLC candle, timestamped by the end of the period it covers.
+#[derive(serde::Serialize, Clone, Copy, Debug)]
+pub struct Candle {
+    pub close_time: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
@@ -0,0 +1,11 @@
+use std::collections::HashMap;
+
+/// An entry of the coin registry.
+#[derive(Clone, Debug)]
+pub struct Coin {
+    pub id: String,
+    pub symbol: String,
+    pub name: String,
+    /// Platform id (`ethereum`) to the contract address on that chain.
+    pub platforms: HashMap<String, String>,
+}
@@ -0,0 +1,155 @@
+use sha3::{Digest, Keccak256};
+
+/// A token contract address as given by a client.
+/// EVM addresses (`0x` or `0X` followed by 40 hex digits) are matched regardless of case,
+/// but a mixed-case address must carry a valid EIP-55 checksum.
+/// Anything else is kept as given, since other chains use case-sensitive encodings,
+/// some of them `0x` prefixed as well (Aptos and Sui types, 64 digit Starknet addresses).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContractAddress {
+    address: String,
+    evm: bool,
+}
+
+impl ContractAddress {
+    pub fn parse(address: &str) -> Result<Self, String> {
+        let address = address.trim();
+        if address.is_empty() {
+            return Err("Contract address is empty".into());
+        }
+        let hex = address
+            .strip_prefix("0x")
+            .or_else(|| address.strip_prefix("0X"))
+            .filter(|hex| hex.len() == 40 && hex.chars().all(|c| c.is_ascii_hexdigit()));
+        let Some(hex) = hex else {
+            return Ok(Self { address: address.to_string(), evm: false });
+        };
+        let mixed_case = hex.chars().any(|c| c.is_ascii_lowercase())
+            && hex.chars().any(|c| c.is_ascii_uppercase());
+        if mixed_case && to_checksum(hex) != hex {
+            return Err(format!("{} has an invalid EIP-55 checksum", address));
+        }
+        Ok(Self { address: format!("0x{}", hex.to_ascii_lowercase()), evm: true })
+    }
+
+    /// Lowercase for EVM addresses, as given otherwise.
+    pub fn as_str(&self) -> &str {
+        &self.address
+    }
+
+    /// Whether the address is an EVM one, whose case carries no meaning.
+    pub fn is_evm(&self) -> bool {
+        self.evm
+    }
+
+    /// The EIP-55 checksummed form of EVM addresses, as given otherwise.
+    pub fn checksummed(&self) -> String {
+        match self.address.strip_prefix("0x") {
+            Some(hex) if self.evm => format!("0x{}", to_checksum(hex)),
+            _ => self.address.clone(),
+        }
+    }
+}
+
+/// Uppercases every hex letter whose nibble in the Keccak-256 hash of the
+/// lowercase address is 8 or more.
+fn to_checksum(hex: &str) -> String {
+    let hex = hex.to_ascii_lowercase();
+    let hash = Keccak256::digest(hex.as_bytes());
+    hex.chars()
+        .enumerate()
+        .map(|(i, c)| {
+            let nibble = (hash[i / 2] >> if i % 2 == 0 { 4 } else { 0 }) & 0x0f;
+            if nibble >= 8 { c.to_ascii_uppercase() } else { c }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Checksummed addresses of the EIP-55 specification.
+    const CHECKSUMMED: [&str; 8] = [
+        "0x52908400098527886E0F7030069857D2E4169EE7",
+        "0x8617E340B3D01FA5F11F306F4090FD50E238070D",
+        "0xde709f2102306220921060314715629080e2fb77",
+        "0x27b1fdb04752bbc536007a920d24acb045561c26",
+        "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed",
+        "0xfB6916095ca1df60bB79Ce92cE3Ea74c37c5d359",
+        "0xdbF03B407c01E7cD3CBea99509d93f8DDDC8C6FB",
+        "0xD1220A0cf47c7B9Be7A2E6BA89F429762e7b9aDb",
+    ];
+
+    #[test]
+    fn checksum_matches_the_eip55_vectors() {
+        for address in CHECKSUMMED {
+            assert_eq!(format!("0x{}", to_checksum(&address[2..])), address);
+        }
+    }
+
+    #[test]
+    fn valid_mixed_case_addresses_are_accepted() {
+        for address in CHECKSUMMED {
+            let parsed = ContractAddress::parse(address).unwrap();
+            assert!(parsed.is_evm());
+            assert_eq!(parsed.as_str(), address.to_ascii_lowercase());
+            assert_eq!(parsed.checksummed(), address);
+        }
+    }
+
+    #[test]
+    fn single_case_addresses_carry_no_checksum() {
+        for address in CHECKSUMMED {
+            let lowercase = format!("0x{}", address[2..].to_ascii_lowercase());
+            let uppercase = format!("0x{}", address[2..].to_ascii_uppercase());
+            assert_eq!(ContractAddress::parse(&lowercase).unwrap().checksummed(), address);
+            assert_eq!(ContractAddress::parse(&uppercase).unwrap().checksummed(), address);
+        }
+    }
+
+    #[test]
+    fn invalid_mixed_case_address_is_rejected() {
+        // The last letter of a checksummed address with its case flipped.
+        let address = "0x5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD";
+        assert!(ContractAddress::parse(address)
+            .unwrap_err()
+            .contains("invalid EIP-55 checksum"));
+    }
+
+    #[test]
+    fn empty_address_is_rejected() {
+        assert!(ContractAddress::parse(" ").is_err());
+    }
+
+    #[test]
+    fn uppercase_prefix_is_an_evm_address_too() {
+        let parsed = ContractAddress::parse("0X5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAed").unwrap();
+        assert!(parsed.is_evm());
+        assert_eq!(parsed.as_str(), "0x5aaeb6053f3e94c9b9a09f33669435e7ef1beaed");
+        assert!(ContractAddress::parse("0X5aAeb6053F3E94C9b9A09f33669435E7Ef1BeAeD").is_err());
+    }
+
+    #[test]
+    fn other_0x_addresses_are_kept_as_given() {
+        for address in [
+            "0x1::aptos_coin::AptosCoin",
+            "0x2::sui::SUI",
+            "0x049D36570D4e46f48e99674bd3fcc84644DdD6b96F7C741B1562B82f9e004dC7",
+        ] {
+            let parsed = ContractAddress::parse(address).unwrap();
+            assert!(!parsed.is_evm());
+            assert_eq!(parsed.as_str(), address);
+            assert_eq!(parsed.checksummed(), address);
+        }
+    }
+
+    #[test]
+    fn non_evm_address_is_kept_as_given() {
+        let address = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+        let parsed = ContractAddress::parse(address).unwrap();
+        assert!(!parsed.is_evm());
+        assert_eq!(parsed.as_str(), address);
+        assert_eq!(parsed.checksummed(), address);
+    }
+}
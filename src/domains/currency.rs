@@ -1,4 +1,5 @@
-#[derive(serde::Serialize, serde::Deserialize)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
 pub enum Currency{
     BTC,
     ETH,
@@ -62,6 +63,68 @@ pub enum Currency{
 }
 
 impl Currency {
+    pub const ALL: [Currency; 59] = [
+        Self::BTC,
+        Self::ETH,
+        Self::LTC,
+        Self::BCH,
+        Self::BNB,
+        Self::EOS,
+        Self::XRP,
+        Self::XLM,
+        Self::LINK,
+        Self::DOT,
+        Self::YFI,
+        Self::USD,
+        Self::AED,
+        Self::ARS,
+        Self::AUD,
+        Self::BDT,
+        Self::BHD,
+        Self::BMD,
+        Self::BRL,
+        Self::CAD,
+        Self::CHF,
+        Self::CLP,
+        Self::CNY,
+        Self::CZK,
+        Self::DKK,
+        Self::EUR,
+        Self::GBP,
+        Self::HKD,
+        Self::HUF,
+        Self::IDR,
+        Self::ILS,
+        Self::INR,
+        Self::JPY,
+        Self::KRW,
+        Self::KWD,
+        Self::LKR,
+        Self::MMK,
+        Self::MXN,
+        Self::MYR,
+        Self::NGN,
+        Self::NOK,
+        Self::NZD,
+        Self::PHP,
+        Self::PKR,
+        Self::PLN,
+        Self::RUB,
+        Self::SAR,
+        Self::SEK,
+        Self::SGD,
+        Self::THB,
+        Self::TRY,
+        Self::TWD,
+        Self::UAH,
+        Self::VEF,
+        Self::VND,
+        Self::ZAR,
+        Self::XDR,
+        Self::XAG,
+        Self::XAU,
+    ];
+
     pub fn as_str(&self)-> &str {
         match self {
             Self::BTC => "btc",
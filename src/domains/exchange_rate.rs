@@ -0,0 +1,12 @@
+use super::Currency;
+
+/// What one BTC is worth in `currency`.
+#[derive(Clone, Debug)]
+pub struct ExchangeRate {
+    pub currency: Currency,
+    pub name: String,
+    pub unit: String,
+    /// `crypto`, `fiat` or `commodity`.
+    pub rate_type: String,
+    pub value: f64,
+}
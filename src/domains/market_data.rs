@@ -0,0 +1,64 @@
+#[derive(serde::Deserialize, serde::Serialize, Default)]
+pub struct MarketData {
+    pub id: Option<String>,
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+    pub image: Option<String>,
+    pub current_price: Option<f64>,
+    pub market_cap: Option<f64>,
+    pub market_cap_rank: Option<i32>,
+    pub fully_diluted_valuation: Option<f64>,
+    pub total_volume: Option<f64>,
+    pub high_24h: Option<f64>,
+    pub low_24h: Option<f64>,
+    pub price_change_24h: Option<f64>,
+    pub price_change_percentage_24h: Option<f64>,
+    pub market_cap_change_24h: Option<f64>,
+    pub market_cap_change_percentage_24h: Option<f64>,
+    pub circulating_supply: Option<f64>,
+    pub total_supply: Option<f64>,
+    pub max_supply: Option<f64>,
+    pub ath: Option<f64>,
+    pub ath_change_percentage: Option<f64>,
+    pub ath_date: Option<String>,
+    pub atl: Option<f64>,
+    pub atl_change_percentage: Option<f64>,
+    pub atl_date: Option<String>,
+    pub roi: Option<Roi>,
+    pub last_updated: Option<String>,
+    pub price_change_percentage_1h_in_currency: Option<f64>,
+    pub price_change_percentage_24h_in_currency: Option<f64>,
+    pub price_change_percentage_7d_in_currency: Option<f64>,
+    pub price_change_percentage_14d_in_currency: Option<f64>,
+    pub price_change_percentage_30d_in_currency: Option<f64>,
+    pub price_change_percentage_200d_in_currency: Option<f64>,
+    pub price_change_percentage_1y_in_currency: Option<f64>,
+    /// Only present when the listing was requested with `sparkline=true`.
+    pub sparkline_in_7d: Option<Sparkline>,
+}
+
+/// Return on investment since the ICO, when CoinGecko knows the ICO price.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, PartialEq)]
+pub struct Roi {
+    pub times: f64,
+    pub currency: String,
+    pub percentage: f64,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Default)]
+pub struct Sparkline {
+    pub price: Vec<f64>,
+}
+
+impl MarketData {
+    /// Checks the fields the storage keys on, so obviously broken rows are
+    /// rejected before they reach the database.
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.id, &self.symbol) {
+            (Some(id), _) if id.is_empty() => Err("id is empty".into()),
+            (None, _) => Err("id is missing".into()),
+            (_, None) => Err("symbol is missing".into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -1,2 +1,14 @@
+mod candle;
+mod coin;
+mod contract_address;
 mod currency;
-pub use currency::Currency;
\ No newline at end of file
+mod exchange_rate;
+mod market_data;
+mod quote;
+pub use candle::{Candle, CandleInterval};
+pub use coin::Coin;
+pub use contract_address::ContractAddress;
+pub use currency::Currency;
+pub use exchange_rate::ExchangeRate;
+pub use market_data::{MarketData, Roi, Sparkline};
+pub use quote::{Quote, TokenQuote};
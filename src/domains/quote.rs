@@ -0,0 +1,28 @@
+use chrono::{DateTime, Utc};
+
+use super::Currency;
+
+/// A single price observation of a coin reported by one price source.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct Quote {
+    pub coin_id: String,
+    pub currency: Currency,
+    pub price: f64,
+    pub volume_24h: Option<f64>,
+    pub last_updated: DateTime<Utc>,
+    pub source: String,
+}
+
+/// The price of a token looked up by contract address rather than coin id.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct TokenQuote {
+    pub platform: String,
+    pub contract_address: String,
+    pub currency: Currency,
+    pub price: f64,
+    pub market_cap: Option<f64>,
+    pub volume_24h: Option<f64>,
+    pub change_percentage_24h: Option<f64>,
+    pub last_updated: DateTime<Utc>,
+    pub source: String,
+}
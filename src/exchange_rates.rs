@@ -0,0 +1,95 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domains::{Currency, ExchangeRate};
+
+/// Replaces the stored rate of every listed currency; unlisted ones keep their last rate.
+pub async fn store_exchange_rates(
+    pool: &PgPool,
+    rates: &[ExchangeRate],
+    source: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            INSERT INTO exchange_rates (currency, name, unit, rate_type, value, source)
+            SELECT *, $6 FROM UNNEST($1::text[], $2::text[], $3::text[], $4::text[], $5::float8[])
+            ON CONFLICT (currency) DO UPDATE SET
+                name = EXCLUDED.name,
+                unit = EXCLUDED.unit,
+                rate_type = EXCLUDED.rate_type,
+                value = EXCLUDED.value,
+                source = EXCLUDED.source,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        &rates.iter().map(|r| r.currency.as_str().to_string()).collect::<Vec<_>>(),
+        &rates.iter().map(|r| r.name.clone()).collect::<Vec<_>>(),
+        &rates.iter().map(|r| r.unit.clone()).collect::<Vec<_>>(),
+        &rates.iter().map(|r| r.rate_type.clone()).collect::<Vec<_>>(),
+        &rates.iter().map(|r| r.value).collect::<Vec<_>>(),
+        source,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// How many units of `to` one unit of `from` is worth.
+pub struct ConversionRate {
+    pub rate: f64,
+    /// When the older of the two rates was stored.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Crosses the BTC rates of both currencies, or `None` when either has no usable rate.
+pub async fn conversion_rate(
+    pool: &PgPool,
+    from: &Currency,
+    to: &Currency,
+) -> Result<Option<ConversionRate>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+            SELECT f.value AS from_value, t.value AS to_value, LEAST(f.updated_at, t.updated_at) AS "updated_at!"
+            FROM exchange_rates f, exchange_rates t
+            WHERE f.currency = $1 AND t.currency = $2
+            "#,
+        from.as_str(),
+        to.as_str(),
+    )
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.and_then(|row| {
+        cross_rate(row.from_value, row.to_value).map(|rate| ConversionRate {
+            rate,
+            updated_at: row.updated_at,
+        })
+    }))
+}
+
+/// Units of `to` one unit of `from` is worth, given what one BTC is worth in each,
+/// or `None` when either value cannot be used.
+pub fn cross_rate(from_value: f64, to_value: f64) -> Option<f64> {
+    let usable = |value: f64| value.is_finite() && value > 0.0;
+    (usable(from_value) && usable(to_value)).then(|| to_value / from_value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates_are_crossed_through_btc() {
+        // One BTC is worth 30000 USD and 27000 EUR.
+        assert_eq!(cross_rate(30000.0, 27000.0), Some(0.9));
+        assert_eq!(cross_rate(27000.0, 30000.0).map(|rate| (rate * 1e6).round() / 1e6), Some(1.111111));
+        assert_eq!(cross_rate(30000.0, 30000.0), Some(1.0));
+    }
+
+    #[test]
+    fn unusable_values_give_no_rate() {
+        assert_eq!(cross_rate(0.0, 27000.0), None);
+        assert_eq!(cross_rate(30000.0, 0.0), None);
+        assert_eq!(cross_rate(-1.0, 27000.0), None);
+        assert_eq!(cross_rate(f64::NAN, 27000.0), None);
+        assert_eq!(cross_rate(30000.0, f64::INFINITY), None);
+    }
+}
@@ -0,0 +1,241 @@
+mod models;
+mod requests;
+pub use models::*;
+pub use requests::*;
+
+use reqwest::{
+    header::{HeaderMap, HeaderValue},
+    Client, StatusCode, Url,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{de::DeserializeOwned, Serialize};
+use std::time::Duration;
+
+use crate::{
+    domains::MarketData,
+    resilience::{retry_after, Backoff, CircuitBreaker, TokenBucket},
+    utils::error_chain_fmt,
+};
+
+pub struct GeckoClient {
+    http_client : Client,
+    url: Url,
+    request_budget: TokenBucket,
+    backoff: Backoff,
+    circuit_breaker: CircuitBreaker,
+}
+
+/// CoinGecko plan the API key belongs to; each plan expects its own header.
+#[derive(serde::Deserialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiPlan {
+    Demo,
+    Pro,
+}
+
+impl ApiPlan {
+    pub fn header(&self) -> &'static str {
+        match self {
+            Self::Demo => "x-cg-demo-api-key",
+            Self::Pro => "x-cg-pro-api-key",
+        }
+    }
+}
+
+/// An API key already checked to fit in a header.
+pub struct GeckoApiKey {
+    plan: ApiPlan,
+    value: HeaderValue,
+}
+
+impl GeckoApiKey {
+    pub fn new(plan: ApiPlan, key: &Secret<String>) -> Result<Self, String> {
+        let mut value = HeaderValue::from_str(key.expose_secret())
+            .map_err(|_| "gecko_client.api_key must only hold visible ASCII characters".to_string())?;
+        value.set_sensitive(true);
+        Ok(Self { plan, value })
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum GeckoError {
+    #[error("Rate limited by CoinGecko")]
+    RateLimited(Option<Duration>),
+    #[error("CoinGecko calls are suspended after repeated failures")]
+    CircuitOpen,
+    #[error("CoinGecko responded with {0}")]
+    ServerError(StatusCode),
+    #[error("Failed to send request to CoinGecko")]
+    RequestError(#[from] reqwest::Error),
+    #[error("Failed to decode CoinGecko response")]
+    DecodeError(#[source] reqwest::Error),
+}
+
+impl std::fmt::Debug for GeckoError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl GeckoError {
+    /// Throttling, 5xx responses, timeouts and connection failures are worth retrying.
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::RateLimited(_) | Self::ServerError(_) => true,
+            Self::RequestError(e) => e.is_timeout() || e.is_connect(),
+            Self::CircuitOpen | Self::DecodeError(_) => false,
+        }
+    }
+
+    /// A 4xx response other than throttling, e.g. an unknown coin or platform.
+    pub fn is_client_error(&self) -> bool {
+        matches!(self, Self::RequestError(e) if e.status().is_some_and(|s| s.is_client_error()))
+    }
+}
+
+impl GeckoClient {
+    pub fn new(
+        url:String,
+        timeout: Duration,
+        api_key: Option<GeckoApiKey>,
+        request_budget: TokenBucket,
+        backoff: Backoff,
+        circuit_breaker: CircuitBreaker,
+    )->Self {
+        let mut headers = HeaderMap::new();
+        if let Some(api_key) = api_key {
+            headers.insert(api_key.plan.header(), api_key.value);
+        }
+        let http_client = Client::builder()
+            .timeout(timeout)
+            .default_headers(headers)
+            .build()
+            .unwrap();
+        let url = Url::parse(&url).expect("Invalid CoinGecko url");
+        Self {
+            http_client,
+            url,
+            request_budget,
+            backoff,
+            circuit_breaker,
+        }
+    }
+
+    /// Time left before CoinGecko is called again, if the circuit breaker is open.
+    pub fn suspended_for(&self) -> Option<Duration> {
+        self.circuit_breaker.open_for()
+    }
+
+    /// `coins/markets`; coins CoinGecko has no data for come back as `None`.
+    pub async fn markets(&self, request: &MarketsRequest) -> Result<Vec<Option<MarketData>>, GeckoError> {
+        self.get(&["coins", "markets"], request).await
+    }
+
+    pub async fn simple_price(&self, request: &SimplePriceRequest) -> Result<SimplePrice, GeckoError> {
+        self.get(&["simple", "price"], request).await
+    }
+
+    /// `simple/token_price/{platform}`; the result is keyed by lowercase contract address.
+    pub async fn token_price(&self, request: &TokenPriceRequest) -> Result<SimplePrice, GeckoError> {
+        self.get(&["simple", "token_price", &request.platform], request).await
+    }
+
+    pub async fn coins_list(&self, request: &CoinsListRequest) -> Result<Vec<CoinListEntry>, GeckoError> {
+        self.get(&["coins", "list"], request).await
+    }
+
+    pub async fn coin(&self, request: &CoinRequest) -> Result<CoinDetail, GeckoError> {
+        self.get(&["coins", &request.id], request).await
+    }
+
+    pub async fn market_chart(&self, request: &MarketChartRequest) -> Result<MarketChart, GeckoError> {
+        self.get(&["coins", &request.id, "market_chart"], request).await
+    }
+
+    pub async fn market_chart_range(&self, request: &MarketChartRangeRequest) -> Result<MarketChart, GeckoError> {
+        self.get(&["coins", &request.id, "market_chart", "range"], request).await
+    }
+
+    pub async fn ohlc(&self, request: &OhlcRequest) -> Result<Vec<Ohlc>, GeckoError> {
+        self.get(&["coins", &request.id, "ohlc"], request).await
+    }
+
+    pub async fn exchange_rates(&self) -> Result<ExchangeRates, GeckoError> {
+        self.get(&["exchange_rates"], &()).await
+    }
+
+    /// Sends a GET to the endpoint made of the percent-encoded `path` segments
+    /// with `query` URL-encoded, and decodes the JSON body.
+    async fn get<Q, R>(&self, path: &[&str], query: &Q) -> Result<R, GeckoError>
+    where
+        Q: Serialize + ?Sized,
+        R: DeserializeOwned,
+    {
+        let mut url = self.url.clone();
+        url.path_segments_mut()
+            .expect("CoinGecko url cannot be a base")
+            .pop_if_empty()
+            .extend(path);
+        self.get_request(url, query)
+            .await?
+            .json::<R>()
+            .await
+            .map_err(GeckoError::DecodeError)
+    }
+
+    async fn get_request<Q: Serialize + ?Sized>(&self, url: Url, query: &Q) -> Result<reqwest::Response, GeckoError> {
+        if !self.circuit_breaker.allow() {
+            return Err(GeckoError::CircuitOpen);
+        }
+        let mut attempt = 0;
+        loop {
+            self.request_budget.acquire().await;
+            match self.send(url.clone(), query).await {
+                Ok(response) => {
+                    self.circuit_breaker.record_success();
+                    return Ok(response);
+                }
+                Err(GeckoError::RateLimited(Some(retry_after))) if retry_after > self.backoff.max => {
+                    // Waiting that long would hold every caller up; the circuit stays open instead.
+                    println!("Rate limited by CoinGecko, suspending calls for {:?}", retry_after);
+                    self.circuit_breaker.trip(retry_after);
+                    return Err(GeckoError::RateLimited(Some(retry_after)));
+                }
+                Err(e) if e.is_retryable() && attempt < self.backoff.max_retries => {
+                    let delay = match e {
+                        GeckoError::RateLimited(Some(retry_after)) => retry_after,
+                        _ => self.backoff.delay(attempt),
+                    };
+                    println!("{}, retrying in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    if e.is_retryable() {
+                        self.circuit_breaker.record_failure();
+                    } else if e.is_client_error() {
+                        // CoinGecko answered, so a trial call of the half-open circuit succeeded.
+                        self.circuit_breaker.record_success();
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    async fn send<Q: Serialize + ?Sized>(&self, url: Url, query: &Q) -> Result<reqwest::Response, GeckoError> {
+        let response = self.http_client
+        .get(url)
+        .query(query)
+        .send()
+        .await?;
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(GeckoError::RateLimited(retry_after(response.headers())));
+        }
+        if status.is_server_error() {
+            return Err(GeckoError::ServerError(status));
+        }
+        Ok(response.error_for_status()?)
+    }
+}
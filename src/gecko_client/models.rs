@@ -0,0 +1,170 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+/// `simple/price`: coin id to fields such as `usd`, `usd_24h_vol` or `last_updated_at`.
+pub type SimplePrice = HashMap<String, HashMap<String, Option<f64>>>;
+
+/// One entry of `coins/list`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct CoinListEntry {
+    pub id: String,
+    pub symbol: String,
+    pub name: String,
+    /// Platform id (`ethereum`) to contract address; only present with `include_platform`.
+    #[serde(default)]
+    pub platforms: HashMap<String, Option<String>>,
+}
+
+/// `coins/{id}` without the optional sections.
+#[derive(Deserialize, Clone, Debug)]
+pub struct CoinDetail {
+    pub id: String,
+    pub symbol: String,
+    pub name: String,
+    #[serde(default)]
+    pub asset_platform_id: Option<String>,
+    #[serde(default)]
+    pub platforms: HashMap<String, Option<String>>,
+    #[serde(default)]
+    pub categories: Vec<Option<String>>,
+    pub market_cap_rank: Option<i32>,
+    pub last_updated: Option<String>,
+}
+
+/// `[timestamp in milliseconds, value]`
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct ChartPoint(pub f64, pub Option<f64>);
+
+/// `coins/{id}/market_chart`.
+#[derive(Deserialize, Clone, Debug)]
+pub struct MarketChart {
+    pub prices: Vec<ChartPoint>,
+    pub market_caps: Vec<ChartPoint>,
+    pub total_volumes: Vec<ChartPoint>,
+}
+
+/// `[close time in milliseconds, open, high, low, close]`; CoinGecko stamps a candle with the end of its period.
+#[derive(Deserialize, Clone, Copy, Debug)]
+pub struct Ohlc(pub f64, pub f64, pub f64, pub f64, pub f64);
+
+/// `exchange_rates`: every rate is expressed against one BTC.
+#[derive(Deserialize, Clone, Debug)]
+pub struct ExchangeRates {
+    pub rates: HashMap<String, ExchangeRate>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct ExchangeRate {
+    pub name: String,
+    pub unit: String,
+    pub value: f64,
+    #[serde(rename = "type")]
+    pub rate_type: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domains::MarketData;
+
+    macro_rules! fixture {
+        ($name:literal) => {
+            include_str!(concat!("../../tests/fixtures/gecko/", $name))
+        };
+    }
+
+    #[test]
+    fn coins_markets_decodes_with_missing_coins() {
+        let markets: Vec<Option<MarketData>> = serde_json::from_str(fixture!("coins_markets.json")).unwrap();
+        assert_eq!(markets.len(), 3);
+        assert!(markets[2].is_none());
+
+        let bitcoin = markets[0].as_ref().unwrap();
+        assert_eq!(bitcoin.id.as_deref(), Some("bitcoin"));
+        assert_eq!(bitcoin.current_price, Some(28012.45));
+        assert_eq!(bitcoin.ath, Some(69045.0));
+        assert_eq!(bitcoin.roi, None);
+        assert_eq!(bitcoin.sparkline_in_7d.as_ref().unwrap().price.len(), 3);
+        assert_eq!(bitcoin.price_change_percentage_7d_in_currency, Some(2.2174));
+
+        let ethereum = markets[1].as_ref().unwrap();
+        assert_eq!(ethereum.max_supply, None);
+        assert_eq!(ethereum.roi.as_ref().unwrap().currency, "btc");
+        assert!(ethereum.sparkline_in_7d.is_none());
+        assert_eq!(ethereum.price_change_percentage_1h_in_currency, None);
+    }
+
+    #[test]
+    fn simple_price_keeps_null_fields() {
+        let prices: SimplePrice = serde_json::from_str(fixture!("simple_price.json")).unwrap();
+        assert_eq!(prices["bitcoin"]["usd"], Some(28012.45));
+        assert_eq!(prices["bitcoin"]["last_updated_at"], Some(1680946262.0));
+        assert_eq!(prices["tether"]["usd_market_cap"], None);
+    }
+
+    #[test]
+    fn token_price_is_keyed_by_contract_address() {
+        let prices: SimplePrice = serde_json::from_str(fixture!("token_price.json")).unwrap();
+        let usdc = &prices["0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48"];
+        assert_eq!(usdc["usd"], Some(1.001));
+        assert!(!usdc.contains_key("usd_market_cap"));
+    }
+
+    #[test]
+    fn coins_list_decodes_every_platform_shape() {
+        let coins: Vec<CoinListEntry> = serde_json::from_str(fixture!("coins_list.json")).unwrap();
+        assert_eq!(coins.len(), 5);
+        assert_eq!(coins[0].platforms.len(), 3);
+        assert_eq!(
+            coins[0].platforms["solana"].as_deref(),
+            Some("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v")
+        );
+        assert!(coins[1].platforms.is_empty());
+        // Native coins are listed with an empty platform and address.
+        assert_eq!(coins[2].platforms[""].as_deref(), Some(""));
+        assert_eq!(coins[3].platforms["ethereum"], None);
+        assert!(coins[3].platforms["arbitrum-one"].is_some());
+        // Without `include_platform` the field is left out.
+        assert!(coins[4].platforms.is_empty());
+    }
+
+    #[test]
+    fn coin_detail_ignores_the_optional_sections() {
+        let coin: CoinDetail = serde_json::from_str(fixture!("coin.json")).unwrap();
+        assert_eq!(coin.id, "usd-coin");
+        assert_eq!(coin.asset_platform_id.as_deref(), Some("ethereum"));
+        assert_eq!(coin.platforms.len(), 2);
+        assert_eq!(coin.categories, [Some("Stablecoins".to_string()), None, Some("USD Stablecoin".to_string())]);
+        assert_eq!(coin.market_cap_rank, Some(4));
+    }
+
+    #[test]
+    fn market_chart_keeps_null_points() {
+        let chart: MarketChart = serde_json::from_str(fixture!("market_chart.json")).unwrap();
+        assert_eq!(chart.prices.len(), 3);
+        assert_eq!(chart.prices[0].0, 1680307200000.0);
+        assert_eq!(chart.prices[1].1, Some(28501.12));
+        assert_eq!(chart.prices[2].1, None);
+        assert_eq!(chart.market_caps.len(), 2);
+        assert_eq!(chart.total_volumes[1].0, 1680314400000.0);
+    }
+
+    #[test]
+    fn ohlc_decodes_positional_candles() {
+        let candles: Vec<Ohlc> = serde_json::from_str(fixture!("ohlc.json")).unwrap();
+        assert_eq!(candles.len(), 3);
+        let Ohlc(close_time, open, high, low, close) = candles[2];
+        assert_eq!(close_time, 1680915600000.0);
+        assert_eq!((open, high, low, close), (28040.8, 28040.8, 27988.4, 28012.45));
+    }
+
+    #[test]
+    fn exchange_rates_decode_the_rate_type() {
+        let rates: ExchangeRates = serde_json::from_str(fixture!("exchange_rates.json")).unwrap();
+        assert_eq!(rates.rates.len(), 4);
+        assert_eq!(rates.rates["usd"].value, 28012.45);
+        assert_eq!(rates.rates["eur"].unit, "€");
+        assert_eq!(rates.rates["xau"].rate_type, "commodity");
+    }
+}
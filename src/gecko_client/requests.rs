@@ -0,0 +1,284 @@
+use serde::{Serialize, Serializer};
+
+use crate::domains::Currency;
+
+/// Query parameters of `coins/markets`.
+#[derive(Serialize, Clone, Debug)]
+pub struct MarketsRequest {
+    pub vs_currency: Currency,
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "comma_separated")]
+    pub ids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<MarketOrder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub per_page: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<u16>,
+    pub sparkline: bool,
+    /// Windows such as `1h` or `7d` to include as `price_change_percentage_<window>_in_currency`.
+    #[serde(skip_serializing_if = "Vec::is_empty", serialize_with = "comma_separated")]
+    pub price_change_percentage: Vec<String>,
+}
+
+impl MarketsRequest {
+    pub fn new(vs_currency: Currency) -> Self {
+        Self {
+            vs_currency,
+            ids: vec![],
+            order: None,
+            per_page: None,
+            page: None,
+            sparkline: false,
+            price_change_percentage: vec![],
+        }
+    }
+}
+
+#[derive(Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketOrder {
+    MarketCapDesc,
+    MarketCapAsc,
+    VolumeDesc,
+    VolumeAsc,
+    IdAsc,
+    IdDesc,
+}
+
+/// Query parameters of `simple/price`.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SimplePriceRequest {
+    #[serde(serialize_with = "comma_separated")]
+    pub ids: Vec<String>,
+    #[serde(serialize_with = "comma_separated")]
+    pub vs_currencies: Vec<Currency>,
+    pub include_market_cap: bool,
+    pub include_24hr_vol: bool,
+    pub include_24hr_change: bool,
+    pub include_last_updated_at: bool,
+}
+
+/// Path and query parameters of `simple/token_price/{platform}`.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct TokenPriceRequest {
+    #[serde(skip)]
+    pub platform: String,
+    #[serde(serialize_with = "comma_separated")]
+    pub contract_addresses: Vec<String>,
+    #[serde(serialize_with = "comma_separated")]
+    pub vs_currencies: Vec<Currency>,
+    pub include_market_cap: bool,
+    pub include_24hr_vol: bool,
+    pub include_24hr_change: bool,
+    pub include_last_updated_at: bool,
+}
+
+/// Query parameters of `coins/list`.
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct CoinsListRequest {
+    pub include_platform: bool,
+}
+
+/// Path and query parameters of `coins/{id}`.
+#[derive(Serialize, Clone, Debug)]
+pub struct CoinRequest {
+    #[serde(skip)]
+    pub id: String,
+    pub localization: bool,
+    pub tickers: bool,
+    pub market_data: bool,
+    pub community_data: bool,
+    pub developer_data: bool,
+    pub sparkline: bool,
+}
+
+impl CoinRequest {
+    /// Only the coin's identity and platforms, without the heavy optional sections.
+    pub fn new(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            localization: false,
+            tickers: false,
+            market_data: false,
+            community_data: false,
+            developer_data: false,
+            sparkline: false,
+        }
+    }
+}
+
+/// Path and query parameters of `coins/{id}/market_chart`.
+#[derive(Serialize, Clone, Debug)]
+pub struct MarketChartRequest {
+    #[serde(skip)]
+    pub id: String,
+    pub vs_currency: Currency,
+    /// Number of days back from now, or `max`.
+    pub days: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+}
+
+/// Path and query parameters of `coins/{id}/market_chart/range`.
+/// Ranges up to a day come back in 5 minute points, shorter than 90 days hourly and daily beyond.
+#[derive(Serialize, Clone, Debug)]
+pub struct MarketChartRangeRequest {
+    #[serde(skip)]
+    pub id: String,
+    pub vs_currency: Currency,
+    /// UNIX timestamp in seconds.
+    pub from: i64,
+    /// UNIX timestamp in seconds.
+    pub to: i64,
+}
+
+/// Path and query parameters of `coins/{id}/ohlc`.
+#[derive(Serialize, Clone, Debug)]
+pub struct OhlcRequest {
+    #[serde(skip)]
+    pub id: String,
+    pub vs_currency: Currency,
+    /// One of 1, 7, 14, 30, 90, 180, 365 or `max`; it also decides the candle size.
+    pub days: String,
+    /// `daily` or `hourly` candles regardless of `days`; paid plans only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub interval: Option<String>,
+}
+
+fn comma_separated<T: Serialize, S: Serializer>(
+    values: &[T],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let values = values
+        .iter()
+        .map(|v| serde_json::to_value(v).map_err(serde::ser::Error::custom))
+        .map(|v| {
+            v.map(|v| match v {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    serializer.serialize_str(&values.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The query string the client sends for `request`.
+    fn query<Q: Serialize>(request: &Q) -> String {
+        reqwest::Client::new()
+            .get("https://api.coingecko.com/api/v3")
+            .query(request)
+            .build()
+            .unwrap()
+            .url()
+            .query()
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    #[test]
+    fn markets_request_leaves_unset_parameters_out() {
+        assert_eq!(query(&MarketsRequest::new(Currency::USD)), "vs_currency=usd&sparkline=false");
+        let request = MarketsRequest {
+            ids: vec!["bitcoin".to_string(), "ethereum".to_string()],
+            order: Some(MarketOrder::VolumeDesc),
+            per_page: Some(250),
+            page: Some(3),
+            sparkline: true,
+            price_change_percentage: vec!["1h".to_string(), "7d".to_string()],
+            ..MarketsRequest::new(Currency::EUR)
+        };
+        assert_eq!(
+            query(&request),
+            "vs_currency=eur&ids=bitcoin%2Cethereum&order=volume_desc&per_page=250&page=3\
+             &sparkline=true&price_change_percentage=1h%2C7d"
+        );
+    }
+
+    #[test]
+    fn simple_price_request_joins_ids_and_currencies() {
+        let request = SimplePriceRequest {
+            ids: vec!["bitcoin".to_string(), "tether".to_string()],
+            vs_currencies: vec![Currency::USD, Currency::BTC],
+            include_24hr_vol: true,
+            include_last_updated_at: true,
+            ..SimplePriceRequest::default()
+        };
+        assert_eq!(
+            query(&request),
+            "ids=bitcoin%2Ctether&vs_currencies=usd%2Cbtc&include_market_cap=false\
+             &include_24hr_vol=true&include_24hr_change=false&include_last_updated_at=true"
+        );
+    }
+
+    #[test]
+    fn token_price_request_keeps_the_platform_out_of_the_query() {
+        let request = TokenPriceRequest {
+            platform: "ethereum".to_string(),
+            contract_addresses: vec!["0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48".to_string()],
+            vs_currencies: vec![Currency::USD],
+            include_last_updated_at: true,
+            ..TokenPriceRequest::default()
+        };
+        assert_eq!(
+            query(&request),
+            "contract_addresses=0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48&vs_currencies=usd\
+             &include_market_cap=false&include_24hr_vol=false&include_24hr_change=false\
+             &include_last_updated_at=true"
+        );
+    }
+
+    #[test]
+    fn coins_list_request() {
+        assert_eq!(query(&CoinsListRequest { include_platform: true }), "include_platform=true");
+    }
+
+    #[test]
+    fn coin_request_turns_the_optional_sections_off() {
+        assert_eq!(
+            query(&CoinRequest::new("usd-coin")),
+            "localization=false&tickers=false&market_data=false&community_data=false\
+             &developer_data=false&sparkline=false"
+        );
+    }
+
+    #[test]
+    fn market_chart_request() {
+        let request = MarketChartRequest {
+            id: "bitcoin".to_string(),
+            vs_currency: Currency::USD,
+            days: "max".to_string(),
+            interval: Some("daily".to_string()),
+        };
+        assert_eq!(query(&request), "vs_currency=usd&days=max&interval=daily");
+        let request = MarketChartRequest { interval: None, ..request };
+        assert_eq!(query(&request), "vs_currency=usd&days=max");
+    }
+
+    #[test]
+    fn market_chart_range_request() {
+        let request = MarketChartRangeRequest {
+            id: "bitcoin".to_string(),
+            vs_currency: Currency::JPY,
+            from: 1672531200,
+            to: 1680307200,
+        };
+        assert_eq!(query(&request), "vs_currency=jpy&from=1672531200&to=1680307200");
+    }
+
+    #[test]
+    fn ohlc_request() {
+        let request = OhlcRequest {
+            id: "ethereum".to_string(),
+            vs_currency: Currency::USD,
+            days: "14".to_string(),
+            interval: None,
+        };
+        assert_eq!(query(&request), "vs_currency=usd&days=14");
+        let request = OhlcRequest { interval: Some("hourly".to_string()), ..request };
+        assert_eq!(query(&request), "vs_currency=usd&days=14&interval=hourly");
+    }
+}
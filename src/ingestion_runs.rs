@@ -0,0 +1,167 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::{domains::Currency, market_sweep::Sweep};
+
+/// One provider and currency fetched for a page of a sweep.
+pub struct IngestionRun {
+    pub sweep_id: i64,
+    pub tier: String,
+    pub provider: String,
+    pub page: i32,
+    pub currency: String,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub rows_fetched: i32,
+    pub rows_stored: i32,
+    pub rows_rejected: i32,
+    pub error: Option<String>,
+}
+
+impl IngestionRun {
+    pub fn start(sweep: &Sweep, provider: &str, currency: &Currency) -> Self {
+        let now = Utc::now();
+        Self {
+            sweep_id: sweep.id,
+            tier: sweep.tier.clone(),
+            provider: provider.to_string(),
+            page: sweep.next_page,
+            currency: currency.as_str().to_string(),
+            started_at: now,
+            finished_at: now,
+            rows_fetched: 0,
+            rows_stored: 0,
+            rows_rejected: 0,
+            error: None,
+        }
+    }
+}
+
+/// Recent runs of a provider, as reported by the status endpoint.
+#[derive(serde::Serialize)]
+pub struct ProviderHealth {
+    pub provider: String,
+    /// Failed runs since the last successful one, within the window looked at.
+    pub failure_streak: i64,
+    pub last_run_at: DateTime<Utc>,
+    pub last_success_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+}
+
+/// Written outside the page transaction so failed pages are logged too.
+pub async fn record_runs(pool: &PgPool, runs: &[IngestionRun]) -> Result<(), sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    for run in runs {
+        sqlx::query!(
+            r#"
+                INSERT INTO ingestion_runs (
+                    sweep_id, tier, provider, page, currency, started_at, finished_at,
+                    rows_fetched, rows_stored, rows_rejected, error
+                ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+                "#,
+            run.sweep_id,
+            run.tier,
+            run.provider,
+            run.page,
+            run.currency,
+            run.started_at,
+            run.finished_at,
+            run.rows_fetched,
+            run.rows_stored,
+            run.rows_rejected,
+            run.error,
+        )
+        .execute(&mut transaction)
+        .await?;
+    }
+    transaction.commit().await
+}
+
+/// Health of every provider that ran since `since`.
+pub async fn provider_health(pool: &PgPool, since: DateTime<Utc>) -> Result<Vec<ProviderHealth>, sqlx::Error> {
+    sqlx::query_as!(
+        ProviderHealth,
+        r#"
+            WITH last_success AS (
+                SELECT provider, MAX(id) AS id, MAX(finished_at) AS finished_at
+                FROM ingestion_runs
+                WHERE error IS NULL AND started_at > $1
+                GROUP BY provider
+            )
+            SELECT
+                runs.provider AS "provider!",
+                COUNT(*) FILTER (WHERE runs.error IS NOT NULL AND runs.id > COALESCE(last_success.id, 0)) AS "failure_streak!",
+                MAX(runs.finished_at) AS "last_run_at!",
+                MAX(last_success.finished_at) AS last_success_at,
+                (
+                    SELECT failed.error FROM ingestion_runs failed
+                    WHERE failed.provider = runs.provider AND failed.error IS NOT NULL AND failed.started_at > $1
+                    ORDER BY failed.id DESC
+                    LIMIT 1
+                ) AS last_error
+            FROM ingestion_runs runs
+            LEFT JOIN last_success ON last_success.provider = runs.provider
+            WHERE runs.started_at > $1
+            GROUP BY runs.provider
+            ORDER BY runs.provider
+            "#,
+        since,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// Deletes the runs started before `before` and returns how many were.
+pub async fn delete_runs_before(pool: &PgPool, before: DateTime<Utc>) -> Result<u64, sqlx::Error> {
+    let result = sqlx::query!(r#"DELETE FROM ingestion_runs WHERE started_at < $1"#, before)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::market_sweep::current_or_start_sweep;
+
+    async fn run_at(pool: &PgPool, provider: &str, started_at: DateTime<Utc>, error: Option<&str>) {
+        let sweep = current_or_start_sweep(pool, "top", 1, 250).await.unwrap();
+        let mut run = IngestionRun::start(&sweep, provider, &Currency::USD);
+        run.started_at = started_at;
+        run.finished_at = started_at;
+        run.error = error.map(str::to_string);
+        record_runs(pool, &[run]).await.unwrap();
+    }
+
+    #[sqlx::test]
+    async fn provider_health_only_counts_runs_within_the_window(pool: PgPool) {
+        let now = Utc::now();
+        run_at(&pool, "coingecko", now - chrono::Duration::days(3), Some("old failure")).await;
+        run_at(&pool, "coingecko", now - chrono::Duration::hours(2), None).await;
+        run_at(&pool, "coingecko", now - chrono::Duration::hours(1), Some("timeout")).await;
+        run_at(&pool, "binance", now - chrono::Duration::days(3), None).await;
+
+        let health = provider_health(&pool, now - chrono::Duration::days(1)).await.unwrap();
+
+        assert_eq!(health.len(), 1);
+        assert_eq!(health[0].provider, "coingecko");
+        assert_eq!(health[0].failure_streak, 1);
+        assert_eq!(health[0].last_error.as_deref(), Some("timeout"));
+    }
+
+    #[sqlx::test]
+    async fn runs_past_their_retention_are_deleted(pool: PgPool) {
+        let now = Utc::now();
+        run_at(&pool, "coingecko", now - chrono::Duration::days(8), None).await;
+        run_at(&pool, "coingecko", now - chrono::Duration::hours(1), None).await;
+
+        let deleted = delete_runs_before(&pool, now - chrono::Duration::days(7)).await.unwrap();
+
+        assert_eq!(deleted, 1);
+        let left: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM ingestion_runs")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(left, 1);
+    }
+}
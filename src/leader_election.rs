@@ -0,0 +1,191 @@
+use chrono::{DateTime, Utc};
+use sqlx::{Connection, PgConnection, PgPool, Postgres, Transaction};
+
+use crate::configuration::{DatabaseSetting, LeaderElectionSetting};
+
+#[derive(serde::Serialize)]
+pub struct Leader {
+    pub instance_id: String,
+    pub acquired_at: DateTime<Utc>,
+    pub heartbeat_at: DateTime<Utc>,
+    /// Whether the advisory lock is still held, i.e. the leader's connection is alive.
+    pub holds_lock: bool,
+}
+
+/// Leadership over the ingestion loop, held through a session level advisory lock
+/// on a dedicated connection. Postgres releases the lock as soon as that connection
+/// drops, which lets a waiting instance take over.
+pub struct Leadership {
+    connection: PgConnection,
+    lock_id: i64,
+    instance_id: String,
+    generation: i64,
+}
+
+/// Fencing token of a leadership: the `worker_leader` generation it was won with.
+#[derive(Clone, Copy, Debug)]
+pub struct Fence {
+    pub lock_id: i64,
+    pub generation: i64,
+}
+
+impl Fence {
+    /// Fails once another instance has won the lock since this fence was issued.
+    /// The row stays locked until the transaction ends, so a new leader cannot
+    /// take over between this check and the commit.
+    pub async fn check(&self, transaction: &mut Transaction<'_, Postgres>) -> Result<(), anyhow::Error> {
+        let generation = sqlx::query_scalar!(
+            "SELECT generation FROM worker_leader WHERE lock_id = $1 FOR SHARE",
+            self.lock_id,
+        )
+        .fetch_optional(&mut *transaction)
+        .await?;
+        if generation != Some(self.generation) {
+            anyhow::bail!("The worker leadership was taken over by another instance");
+        }
+        Ok(())
+    }
+}
+
+impl Leadership {
+    /// Waits until this instance holds the lock.
+    pub async fn acquire(
+        database: &DatabaseSetting,
+        settings: &LeaderElectionSetting,
+        instance_id: &str,
+    ) -> Self {
+        loop {
+            match Self::try_acquire(database, settings.lock_id, instance_id).await {
+                Ok(Some(leadership)) => return leadership,
+                Ok(None) => {}
+                Err(e) => println!("Error: {}", e),
+            }
+            tokio::time::sleep(settings.retry_interval()).await;
+        }
+    }
+
+    async fn try_acquire(
+        database: &DatabaseSetting,
+        lock_id: i64,
+        instance_id: &str,
+    ) -> Result<Option<Self>, sqlx::Error> {
+        let mut connection = PgConnection::connect_with(&database.with_db()).await?;
+        let acquired = sqlx::query_scalar!(r#"SELECT pg_try_advisory_lock($1) AS "acquired!""#, lock_id)
+            .fetch_one(&mut connection)
+            .await?;
+        if !acquired {
+            return Ok(None);
+        }
+        let generation = sqlx::query_scalar!(
+            r#"
+                INSERT INTO worker_leader (lock_id, instance_id, generation) VALUES ($1, $2, 1)
+                ON CONFLICT (lock_id) DO UPDATE SET
+                    instance_id = $2,
+                    generation = worker_leader.generation + 1,
+                    acquired_at = CURRENT_TIMESTAMP,
+                    heartbeat_at = CURRENT_TIMESTAMP
+                RETURNING generation
+                "#,
+            lock_id,
+            instance_id,
+        )
+        .fetch_one(&mut connection)
+        .await?;
+        Ok(Some(Self {
+            connection,
+            lock_id,
+            instance_id: instance_id.to_string(),
+            generation,
+        }))
+    }
+
+    pub fn fence(&self) -> Fence {
+        Fence {
+            lock_id: self.lock_id,
+            generation: self.generation,
+        }
+    }
+
+    /// Heartbeats on the lock connection until it fails, at which point the lock
+    /// is gone and the caller must stop acting as leader.
+    pub async fn keep_alive(&mut self, interval: std::time::Duration) -> sqlx::Error {
+        loop {
+            tokio::time::sleep(interval).await;
+            let heartbeat = sqlx::query!(
+                r#"
+                    UPDATE worker_leader SET heartbeat_at = CURRENT_TIMESTAMP
+                    WHERE lock_id = $1 AND instance_id = $2 AND generation = $3
+                    "#,
+                self.lock_id,
+                self.instance_id,
+                self.generation,
+            )
+            .execute(&mut self.connection)
+            .await;
+            match heartbeat {
+                Err(e) => return e,
+                // Another instance won the lock since.
+                Ok(result) if result.rows_affected() == 0 => return sqlx::Error::RowNotFound,
+                Ok(_) => {}
+            }
+        }
+    }
+}
+
+pub async fn current_leader(pool: &PgPool, lock_id: i64) -> Result<Option<Leader>, sqlx::Error> {
+    // A bigint advisory key shows up in pg_locks split into its high and low 32 bits.
+    sqlx::query_as!(
+        Leader,
+        r#"
+            SELECT instance_id, acquired_at, heartbeat_at, EXISTS (
+                SELECT 1 FROM pg_locks
+                WHERE locktype = 'advisory' AND granted AND objsubid = 1
+                    AND classid::bigint = ($1::bigint >> 32) AND objid::bigint = ($1::bigint & 4294967295)
+            ) AS "holds_lock!"
+            FROM worker_leader
+            WHERE lock_id = $1
+            "#,
+        lock_id,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn set_generation(pool: &PgPool, generation: i64) {
+        sqlx::query(
+            "INSERT INTO worker_leader (lock_id, instance_id, generation) VALUES (1, 'test', $1)
+             ON CONFLICT (lock_id) DO UPDATE SET generation = $1",
+        )
+        .bind(generation)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn fence_holds_while_the_generation_is_unchanged(pool: PgPool) {
+        set_generation(&pool, 3).await;
+        let fence = Fence { lock_id: 1, generation: 3 };
+
+        let mut transaction = pool.begin().await.unwrap();
+
+        assert!(fence.check(&mut transaction).await.is_ok());
+    }
+
+    #[sqlx::test]
+    async fn fence_fails_once_another_instance_took_over(pool: PgPool) {
+        set_generation(&pool, 3).await;
+        let fence = Fence { lock_id: 1, generation: 3 };
+        set_generation(&pool, 4).await;
+
+        let mut transaction = pool.begin().await.unwrap();
+
+        assert!(fence.check(&mut transaction).await.is_err());
+        let unknown = Fence { lock_id: 2, generation: 1 };
+        assert!(unknown.check(&mut transaction).await.is_err());
+    }
+}
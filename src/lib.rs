@@ -1,7 +1,23 @@
 pub mod configuration;
 pub mod gecko_client;
+pub mod binance_client;
 pub mod startup;
 pub mod routes;
 pub mod utils;
 pub mod domains;
-pub mod market_data_worker;
\ No newline at end of file
+pub mod market_data_worker;
+pub mod market_sweep;
+pub mod resilience;
+pub mod price_source;
+pub mod aggregation;
+pub mod worker_schedule;
+pub mod leader_election;
+pub mod cli;
+pub mod shutdown;
+pub mod ingestion_runs;
+pub mod coin_registry;
+pub mod live_token_prices;
+
+pub mod ohlc_candles;
+pub mod backfill;
+pub mod exchange_rates;
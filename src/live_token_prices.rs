@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{
+    domains::{Currency, TokenQuote},
+    resilience::TokenBucket,
+};
+
+/// Token, as a platform and contract address, and the currency it is priced in.
+pub type TokenKey = (String, String, Currency);
+
+/// Live `simple/token_price` lookups of the token price endpoint.
+/// Answers are kept for `ttl`, unknown tokens included, and lookups draw on a
+/// budget of their own, so anonymous traffic cannot spend the worker's requests.
+pub struct LiveTokenPrices {
+    ttl: Duration,
+    budget: TokenBucket,
+    answers: Mutex<HashMap<TokenKey, (Instant, Option<TokenQuote>)>>,
+}
+
+impl LiveTokenPrices {
+    pub fn new(ttl: Duration, budget: TokenBucket) -> Self {
+        Self {
+            ttl,
+            budget,
+            answers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The answer of an earlier lookup that has not expired; `Some(None)` for an unknown token.
+    pub fn cached(&self, key: &TokenKey) -> Option<Option<TokenQuote>> {
+        let answers = self.answers.lock().unwrap();
+        answers
+            .get(key)
+            .filter(|(stored_at, _)| stored_at.elapsed() < self.ttl)
+            .map(|(_, quote)| quote.clone())
+    }
+
+    /// Whether a lookup may be sent upstream now.
+    pub fn try_acquire(&self) -> bool {
+        self.budget.try_acquire().is_ok()
+    }
+
+    /// Keeps the answer of a lookup, dropping the expired ones so the cache
+    /// never outgrows what the budget allows within a `ttl`.
+    pub fn store(&self, key: TokenKey, quote: Option<TokenQuote>) {
+        let mut answers = self.answers.lock().unwrap();
+        answers.retain(|_, (stored_at, _)| stored_at.elapsed() < self.ttl);
+        answers.insert(key, (Instant::now(), quote));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(address: &str) -> TokenKey {
+        ("ethereum".to_string(), address.to_string(), Currency::USD)
+    }
+
+    #[test]
+    fn unknown_tokens_are_cached_too() {
+        let prices = LiveTokenPrices::new(Duration::from_secs(60), TokenBucket::new(1, 1));
+        assert!(prices.cached(&key("0xabc")).is_none());
+
+        prices.store(key("0xabc"), None);
+
+        assert!(prices.cached(&key("0xabc")).is_some_and(|quote| quote.is_none()));
+        assert!(prices.cached(&key("0xdef")).is_none());
+    }
+
+    #[test]
+    fn answers_expire_after_the_ttl() {
+        let prices = LiveTokenPrices::new(Duration::from_millis(20), TokenBucket::new(1, 1));
+        prices.store(key("0xabc"), None);
+
+        std::thread::sleep(Duration::from_millis(20));
+
+        assert!(prices.cached(&key("0xabc")).is_none());
+    }
+
+    #[test]
+    fn lookups_are_refused_once_the_budget_is_spent() {
+        let prices = LiveTokenPrices::new(Duration::from_secs(60), TokenBucket::new(2, 1));
+        assert!(prices.try_acquire());
+        assert!(prices.try_acquire());
+        assert!(!prices.try_acquire());
+    }
+}
@@ -1,32 +1,111 @@
-use std::fmt::{Debug, Display};
-use tokio::task::JoinError;
-use server::startup::Application;
+use std::future::Future;
+use std::sync::Arc;
+use clap::Parser;
+use tokio::task::{JoinError, JoinSet};
+use server::backfill::run_backfill;
+use server::cli::{Cli, Command};
+use server::startup::{Application, WorkerApplication};
 use server::configuration::get_configuration;
-use server::market_data_worker::run_worker_until_stopped;
+use server::market_data_worker::{run_worker_until_stopped, WorkerState};
+use server::shutdown::{shutdown_channel, wait_for_signal};
+
+type TaskOutcome = (&'static str, Result<(), anyhow::Error>);
 
 #[tokio::main]
 async fn main()-> anyhow::Result<()> {
+    let cli = Cli::parse();
     let configuration = get_configuration().expect("Failed to read configuration.");
-    let application = Application::build(configuration.clone()).await?;
-    let application_task = tokio::spawn(application.run_until_stopped());
-    let worker_task = tokio::spawn(run_worker_until_stopped(configuration));
+    let deadline = configuration.application.shutdown_timeout();
+    let state = Arc::new(WorkerState::new(
+        configuration.worker.leader_election.instance_id(),
+        configuration.worker.stall_timeout(),
+    ));
+    let (notify_shutdown, shutdown) = shutdown_channel();
+    let mut tasks = JoinSet::new();
+    let mut servers = vec![];
+    match cli.command() {
+        Command::Serve => {
+            let gecko_client = Arc::new(configuration.gecko_client.clone().client().map_err(anyhow::Error::msg)?);
+            let application = Application::build(configuration, gecko_client, None).await?;
+            servers.push(application.handle());
+            tasks.spawn(named("API", application.run_until_stopped()));
+        }
+        Command::Worker => {
+            let health = WorkerApplication::build(&configuration, state.clone())?;
+            servers.push(health.handle());
+            tasks.spawn(named("Worker health endpoint", health.run_until_stopped()));
+            let gecko_client = Arc::new(configuration.gecko_client.clone().client().map_err(anyhow::Error::msg)?);
+            let worker = run_worker_until_stopped(configuration, gecko_client, state, shutdown);
+            tasks.spawn(named("Background worker", worker));
+        }
+        Command::All => {
+            // A single client, so the API and the worker share one request budget and circuit breaker.
+            let gecko_client = Arc::new(configuration.gecko_client.clone().client().map_err(anyhow::Error::msg)?);
+            let application = Application::build(configuration.clone(), gecko_client.clone(), Some(state.clone())).await?;
+            servers.push(application.handle());
+            tasks.spawn(named("API", application.run_until_stopped()));
+            let worker = run_worker_until_stopped(configuration, gecko_client, state, shutdown);
+            tasks.spawn(named("Background worker", worker));
+        }
+        Command::Backfill(args) => {
+            tasks.spawn(named("Backfill", run_backfill(configuration, args, shutdown)));
+        }
+    }
+
+    // Wait for a signal, or for a task to stop on its own, then stop everything else.
+    let mut failed = false;
     tokio::select! {
-        o = application_task => report_exit("API", o),
-        o = worker_task =>  report_exit("Background worker", o),
-    };
+        _ = wait_for_signal() => println!("Shutdown signal received"),
+        Some(outcome) = tasks.join_next() => failed |= !report_exit(outcome),
+    }
+    let _ = notify_shutdown.send(true);
+    for server in &servers {
+        // Stops accepting connections right away; the returned future only tracks the drain.
+        drop(server.stop(true));
+    }
+    let drained = tokio::time::timeout(deadline, async {
+        let mut failed = false;
+        while let Some(outcome) = tasks.join_next().await {
+            failed |= !report_exit(outcome);
+        }
+        failed
+    })
+    .await;
+    match drained {
+        Ok(drain_failed) => failed |= drain_failed,
+        Err(_) => {
+            println!("Shutdown deadline of {:?} exceeded, aborting the remaining tasks", deadline);
+            tasks.shutdown().await;
+            failed = true;
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
     Ok(())
 }
 
-fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+async fn named<E: Into<anyhow::Error>>(
+    task_name: &'static str,
+    task: impl Future<Output = Result<(), E>>,
+) -> TaskOutcome {
+    (task_name, task.await.map_err(Into::into))
+}
+
+/// Prints how a task ended and returns whether it ended cleanly.
+fn report_exit(outcome: Result<TaskOutcome, JoinError>) -> bool {
     match outcome {
-        Ok(Ok(())) => {
-            println!("{} has exited", task_name)
+        Ok((task_name, Ok(()))) => {
+            println!("{} has exited", task_name);
+            true
         }
-        Ok(Err(e)) => {
-            print!("{} has exited with an error: {}", task_name, e)
+        Ok((task_name, Err(e))) => {
+            println!("{} has exited with an error: {:?}", task_name, e);
+            false
         }
         Err(e) => {
-            print!("{} has panicked: {}", task_name, e)
+            println!("A task has panicked: {}", e);
+            false
         }
     }
-}
\ No newline at end of file
+}
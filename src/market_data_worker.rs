@@ -1,60 +1,811 @@
-use sqlx::PgPool;
+use std::future::Future;
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use sqlx::{Acquire, PgPool, Postgres, Transaction};
 
-use crate::{domains::Currency,
-    configuration::Settings, 
+use crate::{domains::{Candle, CandleInterval, Currency, MarketData},
+    aggregation::aggregate_stored_prices,
+    configuration::{AggregationSetting, OhlcSetting, Settings, TierSetting, WorkerSetting},
+    gecko_client::GeckoClient,
+    leader_election::{Fence, Leadership},
+    shutdown::Shutdown,
     startup::get_connection_pool, 
-    gecko_client::GeckoClient, 
-    routes::{coin_market_details, store_market_data}
+    ingestion_runs::{delete_runs_before, record_runs, IngestionRun},
+    market_sweep::{advance_sweep, current_or_start_sweep, finish_sweep, PageStats, Sweep},
+    price_source::{MarketPage, PriceSource, PriceSourceError},
+    routes::{store_market_data, store_market_page, store_rejected_market_data},
+    coin_registry::store_coins,
+    exchange_rates::store_exchange_rates,
+    ohlc_candles::store_candles,
+    worker_schedule::{
+        next_due_times, schedule_next_run, COINS_LIST_JOB, EXCHANGE_RATES_JOB, INGESTION_RUNS_RETENTION_JOB,
+    },
 };
 
+/// What every page of the ingestion loop works with.
+struct Ingestion {
+    pool: PgPool,
+    providers: Vec<Arc<dyn PriceSource>>,
+    currencies: Vec<Currency>,
+    settings: WorkerSetting,
+    aggregation: AggregationSetting,
+}
+
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
+    /// Served by a provider that does not paginate while the ones that do are
+    /// suspended: its listing was stored, but the sweep stays on its page.
+    Fallback,
+}
+
+/// What the worker is doing, as reported by its health endpoint.
+pub struct WorkerState {
+    pub instance_id: String,
+    leader: AtomicBool,
+    last_page_at: Mutex<Option<DateTime<Utc>>>,
+    stall_timeout: Duration,
+    /// When the loop is considered stalled unless it moves on; unset while not leading.
+    progress_deadline: Mutex<Option<Instant>>,
+}
+
+impl WorkerState {
+    pub fn new(instance_id: String, stall_timeout: Duration) -> Self {
+        Self {
+            instance_id,
+            leader: AtomicBool::new(false),
+            last_page_at: Mutex::new(None),
+            stall_timeout,
+            progress_deadline: Mutex::new(None),
+        }
+    }
+    pub fn is_leader(&self) -> bool {
+        self.leader.load(Ordering::Relaxed)
+    }
+    /// When the worker last went through a page, empty or not.
+    pub fn last_page_at(&self) -> Option<DateTime<Utc>> {
+        *self.last_page_at.lock().unwrap()
+    }
+    /// Whether the loop has gone past its pause by more than the stall timeout.
+    pub fn is_stalled(&self) -> bool {
+        self.progress_deadline
+            .lock()
+            .unwrap()
+            .is_some_and(|deadline| Instant::now() > deadline)
+    }
+    fn set_leader(&self, leader: bool) {
+        self.leader.store(leader, Ordering::Relaxed);
+        *self.progress_deadline.lock().unwrap() = leader.then(|| Instant::now() + self.stall_timeout);
+    }
+    /// The loop is about to spend `duration` waiting on purpose.
+    fn expect_progress_within(&self, duration: Duration) {
+        *self.progress_deadline.lock().unwrap() = Some(Instant::now() + duration + self.stall_timeout);
+    }
+    fn record_page(&self) {
+        *self.last_page_at.lock().unwrap() = Some(Utc::now());
+    }
 }
 
-pub async fn run_worker_until_stopped(configuration:Settings) -> Result<(), anyhow::Error> {
+/// Runs the ingestion loop while this instance is the leader, until the shutdown
+/// is requested. A page in progress is then rolled back and fetched again by the next leader.
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    gecko_client: Arc<GeckoClient>,
+    state: Arc<WorkerState>,
+    mut shutdown: Shutdown,
+) -> Result<(), anyhow::Error> {
+    if !configuration.worker.enabled {
+        println!("Background worker is disabled");
+        // Returning early would stop the process.
+        shutdown.requested().await;
+        return Ok(());
+    }
     let connection_pool = get_connection_pool(&configuration.database);
-    let gecko_client = configuration.gecko_client.client();
-    worker_loop(connection_pool, gecko_client).await
+    let currencies = configuration.worker.currencies().map_err(anyhow::Error::msg)?;
+    let providers = build_providers(&configuration, gecko_client)?;
+    if let Some(currency) = currencies
+        .iter()
+        .find(|c| !providers[0].supported_currencies().contains(c))
+    {
+        anyhow::bail!("{} does not support {}", providers[0].name(), currency.as_str());
+    }
+    let ingestion = Ingestion {
+        pool: connection_pool,
+        providers,
+        currencies,
+        settings: configuration.worker.clone(),
+        aggregation: configuration.aggregation.clone(),
+    };
+    let election = &configuration.worker.leader_election;
+    let instance_id = &state.instance_id;
+    let result = loop {
+        let mut leadership = tokio::select! {
+            leadership = Leadership::acquire(&configuration.database, election, instance_id) => leadership,
+            _ = shutdown.requested() => break Ok(()),
+        };
+        println!("{} is now the worker leader", instance_id);
+        state.set_leader(true);
+        // Losing the lock connection cancels the loop; an unfinished page
+        // transaction is rolled back and picked up by the next leader.
+        let fence = leadership.fence();
+        tokio::select! {
+            result = worker_loop(&ingestion, state.clone(), shutdown.clone(), fence) => break result,
+            e = leadership.keep_alive(election.heartbeat_interval()) => {
+                println!("{} lost the worker leadership: {}", instance_id, e);
+                state.set_leader(false);
+            }
+        }
+    };
+    // The leadership was dropped with the loop, closing the lock connection.
+    state.set_leader(false);
+    ingestion.pool.close().await;
+    result
 }
 
-async fn worker_loop(pool: PgPool, gecko_client:GeckoClient) -> Result<(),anyhow::Error> {
-    let mut count =1;
-    
+/// Instantiates the configured providers, keeping their order of preference.
+fn build_providers(
+    configuration: &Settings,
+    gecko_client: Arc<GeckoClient>,
+) -> Result<Vec<Arc<dyn PriceSource>>, anyhow::Error> {
+    let providers = configuration
+        .worker
+        .providers
+        .iter()
+        .map(|name| -> Result<Arc<dyn PriceSource>, anyhow::Error> {
+            match name.as_str() {
+                "coingecko" => Ok(gecko_client.clone()),
+                "binance" => Ok(Arc::new(configuration.binance_client.clone().source())),
+                _ => anyhow::bail!("Unknown provider: {}", name),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(providers)
+}
+
+async fn worker_loop(
+    ingestion: &Ingestion,
+    state: Arc<WorkerState>,
+    mut shutdown: Shutdown,
+    fence: Fence,
+) -> Result<(), anyhow::Error> {
+    let Ingestion { pool, providers, settings, .. } = ingestion;
+    let mut serving = providers[0].name();
+    let tiers = &settings.tiers;
+    let ohlc_intervals: &[CandleInterval] = if settings.ohlc.coins.is_empty() {
+        &[]
+    } else {
+        &settings.ohlc.intervals
+    };
+    let schedule_names: Vec<String> = tiers
+        .iter()
+        .map(|t| t.schedule_name())
+        .chain([
+            COINS_LIST_JOB.to_string(),
+            EXCHANGE_RATES_JOB.to_string(),
+            INGESTION_RUNS_RETENTION_JOB.to_string(),
+        ])
+        .chain(ohlc_intervals.iter().map(|i| OhlcSetting::schedule_name(*i)))
+        .collect();
     loop {
-        match try_execute_task(&pool, &gecko_client, count).await {
+        if shutdown.is_requested() {
+            return Ok(());
+        }
+        state.expect_progress_within(Duration::ZERO);
+        // The first provider whose circuit is not open serves the sweep, preferring the ones
+        // that paginate, so the worker falls back down the list and returns to the primary
+        // once it recovers.
+        let Some(primary) = providers
+            .iter()
+            .position(|p| p.paginates() && p.suspended_for().is_none())
+            .or_else(|| providers.iter().position(|p| p.suspended_for().is_none()))
+        else {
+            let delay = providers
+                .iter()
+                .filter_map(|p| p.suspended_for())
+                .min()
+                .unwrap_or(settings.error_delay());
+            println!("Every provider is suspended, waiting for {:?}", delay);
+            pause(&state, &mut shutdown, delay).await;
+            continue;
+        };
+        if providers[primary].name() != serving {
+            println!("Switching provider from {} to {}", serving, providers[primary].name());
+            serving = providers[primary].name();
+        }
+        let due_times = match next_due_times(pool, &schedule_names).await {
+            Ok(due_times) => due_times,
+            Err(e) => {
+                println!("Error: {}", e);
+                pause(&state, &mut shutdown, settings.error_delay()).await;
+                continue;
+            }
+        };
+        let now = Utc::now();
+        if due_times.get(COINS_LIST_JOB).is_none_or(|due| *due <= now) {
+            let Some(result) = until_shutdown(&mut shutdown, refresh_coin_registry(ingestion)).await else {
+                return Ok(());
+            };
+            let next_due_at = match result {
+                Ok(count) => {
+                    println!("Coin registry refreshed with {} coins", count);
+                    now + settings.coins_refresh_interval()
+                }
+                Err(e) => {
+                    println!("Error: {}", e);
+                    // The list is large, so a failed refresh is retried sparingly.
+                    now + chrono::Duration::hours(1)
+                }
+            };
+            if let Err(e) = schedule_next_run(pool, COINS_LIST_JOB, next_due_at).await {
+                println!("Error: {}", e);
+            }
+            continue;
+        }
+        if due_times.get(EXCHANGE_RATES_JOB).is_none_or(|due| *due <= now) {
+            let Some(result) = until_shutdown(&mut shutdown, refresh_exchange_rates(ingestion)).await else {
+                return Ok(());
+            };
+            match result {
+                Ok(count) => println!("Exchange rates refreshed for {} currencies", count),
+                // Derived prices keep using the last stored rates until the next refresh.
+                Err(e) => println!("Error: {}", e),
+            }
+            let next_due_at = now + settings.exchange_rates_refresh_interval();
+            if let Err(e) = schedule_next_run(pool, EXCHANGE_RATES_JOB, next_due_at).await {
+                println!("Error: {}", e);
+            }
+            continue;
+        }
+        if due_times.get(INGESTION_RUNS_RETENTION_JOB).is_none_or(|due| *due <= now) {
+            match delete_runs_before(pool, now - settings.ingestion_runs_retention()).await {
+                Ok(count) => println!("{} ingestion runs past their retention deleted", count),
+                Err(e) => println!("Error: {}", e),
+            }
+            let next_due_at = now + chrono::Duration::hours(1);
+            if let Err(e) = schedule_next_run(pool, INGESTION_RUNS_RETENTION_JOB, next_due_at).await {
+                println!("Error: {}", e);
+            }
+            continue;
+        }
+        if let Some(interval) = ohlc_intervals
+            .iter()
+            .find(|i| due_times.get(&OhlcSetting::schedule_name(**i)).is_none_or(|due| *due <= now))
+        {
+            let Some(stored) = until_shutdown(&mut shutdown, refresh_ohlc(ingestion, *interval)).await else {
+                return Ok(());
+            };
+            println!("{} OHLC candles refreshed: {} stored", interval.as_str(), stored);
+            let next_due_at = now + interval.duration();
+            if let Err(e) = schedule_next_run(pool, &OhlcSetting::schedule_name(*interval), next_due_at).await {
+                println!("Error: {}", e);
+            }
+            continue;
+        }
+        // Tiers are listed by priority: a due top tier is always served before the tail.
+        let Some(tier) = tiers
+            .iter()
+            .find(|t| due_times.get(&t.schedule_name()).is_none_or(|due| *due <= now))
+        else {
+            let next_due = due_times.values().min().copied().unwrap_or(now);
+            let delay = (next_due - now).to_std().unwrap_or_default();
+            println!("No tier is due, waiting for {:?}", delay);
+            pause(&state, &mut shutdown, delay).await;
+            continue;
+        };
+        let sweep = match current_or_start_sweep(pool, &tier.name, tier.first_page(settings.page_size), settings.page_size).await {
+            Ok(sweep) => sweep,
+            Err(e) => {
+                println!("Error: {}", e);
+                pause(&state, &mut shutdown, settings.error_delay()).await;
+                continue;
+            }
+        };
+        let mut runs = vec![];
+        let page = try_execute_task(ingestion, primary, &sweep, fence, &mut runs);
+        let Some(outcome) = until_shutdown(&mut shutdown, page).await else {
+            return Ok(());
+        };
+        if let Err(e) = &outcome {
+            // The page transaction was rolled back, including the rows of the runs that went through.
+            for run in runs.iter_mut().filter(|run| run.error.is_none()) {
+                run.rows_stored = 0;
+                run.error = Some(format!("Page rolled back: {}", e));
+            }
+        }
+        if let Err(e) = record_runs(pool, &runs).await {
+            println!("Error: {}", e);
+        }
+        match outcome {
             Ok(ExecutionOutcome::EmptyQueue) => {
-                println!("Empty queue, waiting for 360 seconds");
-                tokio::time::sleep(std::time::Duration::from_secs(360)).await;
+                state.record_page();
+                finish_tier(pool, tier, &sweep, true).await
             }
             Err(e) => {
                 println!("Error: {}", e);
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                pause(&state, &mut shutdown, settings.error_delay()).await;
             }
             Ok(ExecutionOutcome::TaskCompleted) => {
                 println!("Task completed successfully");
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
-                count += 1;
+                state.record_page();
+                let last_page_reached = tier
+                    .last_page(settings.page_size)
+                    .is_some_and(|last| sweep.next_page >= last);
+                let max_pages_reached = settings
+                    .max_pages
+                    .is_some_and(|max| sweep.pages_fetched + 1 >= max as i32);
+                if last_page_reached || max_pages_reached {
+                    finish_tier(pool, tier, &sweep, last_page_reached).await;
+                }
+                pause(&state, &mut shutdown, settings.page_delay()).await;
+            }
+            Ok(ExecutionOutcome::Fallback) => {
+                state.record_page();
+                // Meanwhile the fallback listing is refreshed at the pace of the tier.
+                let delay = providers
+                    .iter()
+                    .filter(|p| p.paginates())
+                    .filter_map(|p| p.suspended_for())
+                    .min()
+                    .unwrap_or_default()
+                    .min(tier.interval().to_std().unwrap_or_default())
+                    .max(settings.page_delay());
+                println!(
+                    "Sweep {} of tier {} stays on page {} until a paginating provider recovers, waiting for {:?}",
+                    sweep.id, tier.name, sweep.next_page, delay
+                );
+                pause(&state, &mut shutdown, delay).await;
             }
-            
         }
     }
 }
 
-async fn try_execute_task(pool: &PgPool, client: &GeckoClient, count: u16) -> Result<ExecutionOutcome, anyhow::Error> {
-    let mut transaction = pool.begin().await?;
-    let result = coin_market_details(client, &Currency::USD, count).await?;
-    if result.is_empty() {
-        return Ok(ExecutionOutcome::EmptyQueue);
+/// Replaces the coin registry with the list of the first available provider that keeps one.
+async fn refresh_coin_registry(ingestion: &Ingestion) -> Result<usize, anyhow::Error> {
+    let available = ingestion.providers.iter().filter(|p| p.suspended_for().is_none());
+    for provider in available {
+        if let Some(coins) = provider.list_coins().await? {
+            store_coins(&ingestion.pool, &coins).await?;
+            return Ok(coins.len());
+        }
+    }
+    anyhow::bail!("No available provider keeps a coin registry")
+}
+
+/// Stores the BTC exchange rates of the first available provider that has them.
+async fn refresh_exchange_rates(ingestion: &Ingestion) -> Result<usize, anyhow::Error> {
+    let available = ingestion.providers.iter().filter(|p| p.suspended_for().is_none());
+    for provider in available {
+        if let Some(rates) = provider.fetch_exchange_rates().await? {
+            store_exchange_rates(&ingestion.pool, &rates, provider.name()).await?;
+            return Ok(rates.len());
+        }
+    }
+    anyhow::bail!("No available provider serves exchange rates")
+}
+
+/// Refreshes the `interval` candles of every tracked coin in every worker currency.
+/// A coin that fails is skipped until the next refresh. Returns the candles stored.
+async fn refresh_ohlc(ingestion: &Ingestion, interval: CandleInterval) -> usize {
+    let Ingestion { pool, providers, currencies, settings, .. } = ingestion;
+    let mut stored = 0;
+    for id in &settings.ohlc.coins {
+        for currency in currencies {
+            let result: Result<usize, anyhow::Error> = async {
+                let Some((source, candles)) = fetch_candles(providers, id, currency, interval).await? else {
+                    anyhow::bail!("No available provider serves candles in {}", currency.as_str());
+                };
+                store_candles(pool, id, currency, interval, source, &candles).await?;
+                Ok(candles.len())
+            }
+            .await;
+            match result {
+                Ok(count) => stored += count,
+                Err(e) => println!(
+                    "Skipping {} candles of {} in {} because of Error: {}",
+                    interval.as_str(), id, currency.as_str(), e
+                ),
+            }
+        }
+    }
+    stored
+}
+
+/// Candles from the first available provider that serves them, along with its name.
+async fn fetch_candles(
+    providers: &[Arc<dyn PriceSource>],
+    id: &str,
+    currency: &Currency,
+    interval: CandleInterval,
+) -> Result<Option<(&'static str, Vec<Candle>)>, PriceSourceError> {
+    let available = providers
+        .iter()
+        .filter(|p| p.suspended_for().is_none() && p.supported_currencies().contains(currency));
+    for provider in available {
+        if let Some(candles) = provider.fetch_ohlc(id, currency, interval).await? {
+            return Ok(Some((provider.name(), candles)));
+        }
+    }
+    Ok(None)
+}
+
+/// Runs `job` unless the shutdown is requested first, in which case it is dropped:
+/// retries waiting on a provider are abandoned, and an unfinished page transaction is rolled back.
+async fn until_shutdown<T>(shutdown: &mut Shutdown, job: impl Future<Output = T>) -> Option<T> {
+    tokio::select! {
+        output = job => Some(output),
+        _ = shutdown.requested() => None,
+    }
+}
+
+/// Sleeps for `delay`, waking up early when the shutdown is requested.
+async fn pause(state: &WorkerState, shutdown: &mut Shutdown, delay: Duration) {
+    state.expect_progress_within(delay);
+    tokio::select! {
+        _ = tokio::time::sleep(delay) => {},
+        _ = shutdown.requested() => {},
+    }
+}
+
+/// Closes the tier's sweep and schedules its next one one interval after this one started.
+/// `completed` tells a sweep that reached the last page from one cut short by `max_pages`.
+async fn finish_tier(pool: &PgPool, tier: &TierSetting, sweep: &Sweep, completed: bool) {
+    match finish_sweep(pool, sweep.id, completed).await {
+        Ok(sweep) => println!(
+            "Sweep {} of tier {} {}: {} pages, {} coins stored, {} skipped",
+            sweep.id,
+            sweep.tier,
+            if completed { "finished" } else { "stopped before its last page" },
+            sweep.pages_fetched,
+            sweep.coins_stored,
+            sweep.coins_skipped
+        ),
+        Err(e) => println!("Error: {}", e),
+    }
+    let next_due_at = sweep.started_at + tier.interval();
+    match schedule_next_run(pool, &tier.schedule_name(), next_due_at).await {
+        Ok(()) => println!("Tier {} is next due at {}", tier.name, next_due_at),
+        Err(e) => println!("Error: {}", e),
+    }
+}
+
+async fn try_execute_task(
+    ingestion: &Ingestion,
+    primary: usize,
+    sweep: &Sweep,
+    fence: Fence,
+    runs: &mut Vec<IngestionRun>,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let Ingestion { pool, providers, currencies, settings, aggregation } = ingestion;
+    let source = &providers[primary];
+    // The listing of a provider that does not paginate says nothing about the page the
+    // sweep is on, so its whole listing is stored and the sweep cursor is left alone.
+    // Without any provider that paginates, that listing is all there is to sweep.
+    let fallback = !source.paginates() && providers.iter().any(|p| p.paginates());
+    let page = MarketPage {
+        page: if fallback { 1 } else { u16::try_from(sweep.next_page)? },
+        per_page: settings.page_size,
+        order: settings.order,
+        sparkline: settings.sparkline,
     };
-    for data in &result {
-        if let Some(data) = data {
-            if let Err(e) = store_market_data(&mut transaction, data).await{
-                println!("Skipping a coin data because of Error: {}", e);
+    let mut transaction = pool.begin().await?;
+    let mut stats = PageStats::default();
+    let mut ids: Vec<String> = vec![];
+    let supported = source.supported_currencies();
+    // Every currency is stored under its own savepoint: one that fails to fetch is
+    // recorded in its run and rolled back alone, keeping the rows of the others.
+    let mut failures = vec![];
+    for currency in currencies.iter().filter(|c| supported.contains(c)) {
+        let mut savepoint = transaction.begin().await?;
+        match ingest(&mut savepoint, source.as_ref(), currency, &page, sweep, &mut stats, runs).await {
+            Ok(stored) => {
+                savepoint.commit().await?;
+                ids.extend(stored);
+            }
+            Err(e) if e.is::<PriceSourceError>() => {
+                savepoint.rollback().await?;
+                println!("Skipping {} data in {} because of Error: {}", source.name(), currency.as_str(), e);
+                failures.push(e);
             }
+            Err(e) => return Err(e),
         }
     }
+    if ids.is_empty() {
+        // A page is only known to be empty when every currency came back empty.
+        return match failures.into_iter().next() {
+            Some(e) => Err(e),
+            None if fallback => Ok(ExecutionOutcome::Fallback),
+            None => Ok(ExecutionOutcome::EmptyQueue),
+        };
+    }
+    ids.sort();
+    ids.dedup();
+    // The remaining providers are paged in step with the serving one and stored
+    // next to its rows; a failing secondary provider never fails the page.
+    let secondary_sources = providers
+        .iter()
+        .enumerate()
+        .filter(|(i, p)| *i != primary && p.suspended_for().is_none())
+        .map(|(_, p)| p);
+    for secondary in secondary_sources {
+        let supported = secondary.supported_currencies();
+        for currency in currencies.iter().filter(|c| supported.contains(c)) {
+            match ingest(&mut transaction, secondary.as_ref(), currency, &page, sweep, &mut stats, runs).await {
+                Ok(_) => {}
+                Err(e) if e.is::<PriceSourceError>() => {
+                    println!("Skipping {} data because of Error: {}", secondary.name(), e)
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    for currency in currencies {
+        aggregate_stored_prices(&mut transaction, &ids, currency, aggregation).await?;
+    }
+    // Only the current leader may commit, even if the lock was lost mid-page.
+    fence.check(&mut transaction).await?;
+    if fallback {
+        transaction.commit().await?;
+        return Ok(ExecutionOutcome::Fallback);
+    }
+    advance_sweep(&mut transaction, sweep.id, &stats).await?;
     transaction.commit().await?;
     Ok(ExecutionOutcome::TaskCompleted)
 }
 
+/// Fetches one currency of the page from `source` and stores it, logging the
+/// attempt in `runs`. Returns the ids of the coins on the page.
+async fn ingest(
+    transaction: &mut Transaction<'_, Postgres>,
+    source: &dyn PriceSource,
+    currency: &Currency,
+    page: &MarketPage,
+    sweep: &Sweep,
+    stats: &mut PageStats,
+    runs: &mut Vec<IngestionRun>,
+) -> Result<Vec<String>, anyhow::Error> {
+    let mut run = IngestionRun::start(sweep, source.name(), currency);
+    let (stored_before, skipped_before) = (stats.coins_stored, stats.coins_skipped);
+    let result: Result<Vec<String>, anyhow::Error> = async {
+        let result = source.list_markets(currency, page).await?;
+        run.rows_fetched = result.len() as i32;
+        store_page(transaction, &result, currency, source.name(), stats).await?;
+        Ok(result.into_iter().filter_map(|data| data.id).collect())
+    }
+    .await;
+    run.finished_at = Utc::now();
+    run.rows_stored = stats.coins_stored - stored_before;
+    run.rows_rejected = stats.coins_skipped - skipped_before;
+    if let Err(e) = &result {
+        run.error = Some(e.to_string());
+    }
+    runs.push(run);
+    result
+}
+
+/// Stores a page with a single bulk upsert. When that fails, every row is
+/// retried under its own savepoint: a failing row is rolled back and recorded in
+/// `rejected_market_data` without poisoning the page transaction.
+async fn store_page(
+    transaction: &mut Transaction<'_, Postgres>,
+    page: &[MarketData],
+    currency: &Currency,
+    source: &str,
+    stats: &mut PageStats,
+) -> Result<(), anyhow::Error> {
+    let mut valid = Vec::with_capacity(page.len());
+    for data in page {
+        match data.validate() {
+            Ok(()) => valid.push(data),
+            Err(error) => reject(transaction, data, currency, source, &error, stats).await?,
+        }
+    }
+    let mut savepoint = transaction.begin().await?;
+    match store_market_page(&mut savepoint, &valid, currency, source).await {
+        Ok(()) => {
+            savepoint.commit().await?;
+            stats.coins_stored += valid.len() as i32;
+            return Ok(());
+        }
+        Err(e) => {
+            savepoint.rollback().await?;
+            println!("Bulk upsert failed, storing the page row by row: {}", e);
+        }
+    }
+    for data in valid {
+        let mut savepoint = transaction.begin().await?;
+        match store_market_data(&mut savepoint, data, currency, source).await {
+            Ok(()) => {
+                savepoint.commit().await?;
+                stats.coins_stored += 1;
+            }
+            Err(e) => {
+                savepoint.rollback().await?;
+                reject(transaction, data, currency, source, &format!("{:?}", e), stats).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn reject(
+    transaction: &mut Transaction<'_, Postgres>,
+    data: &MarketData,
+    currency: &Currency,
+    source: &str,
+    error: &str,
+    stats: &mut PageStats,
+) -> Result<(), anyhow::Error> {
+    stats.coins_skipped += 1;
+    println!("Skipping a coin data because of Error: {}", error);
+    store_rejected_market_data(transaction, data, currency, source, error).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        configuration::get_configuration,
+        market_sweep::{active_sweeps, last_finished_sweeps},
+        price_source::fake::FakeSource,
+        routes::worker_health_check,
+        shutdown::shutdown_channel,
+    };
+    use actix_web::http::StatusCode;
+
+    /// A single unbounded tier of two coin pages, starting on page 3.
+    fn ingestion(pool: PgPool, providers: Vec<Arc<dyn PriceSource>>) -> Ingestion {
+        let configuration = get_configuration().expect("Failed to read configuration.");
+        let mut settings = configuration.worker;
+        settings.page_size = 2;
+        settings.page_delay_seconds = 0;
+        settings.max_pages = None;
+        settings.ohlc.coins = vec![];
+        settings.tiers = vec![TierSetting {
+            name: "tail".to_string(),
+            min_rank: 5,
+            max_rank: None,
+            interval_seconds: 3600,
+        }];
+        Ingestion {
+            pool,
+            providers,
+            currencies: vec![Currency::USD],
+            settings,
+            aggregation: configuration.aggregation,
+        }
+    }
+
+    const FENCE: Fence = Fence { lock_id: 1, generation: 1 };
+
+    async fn lead(pool: &PgPool) {
+        sqlx::query("INSERT INTO worker_leader (lock_id, instance_id, generation) VALUES (1, 'test', 1)")
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+
+    fn listing() -> Vec<Vec<&'static str>> {
+        vec![vec!["a", "b"], vec!["c", "d"], vec!["e", "f"]]
+    }
+
+    async fn run_for(ingestion: &Ingestion, duration: Duration) {
+        let (notify_shutdown, shutdown) = shutdown_channel();
+        let state = Arc::new(WorkerState::new("test".to_string(), Duration::from_secs(60)));
+        let stop = async {
+            tokio::time::sleep(duration).await;
+            notify_shutdown.send(true).unwrap();
+        };
+        let (result, ()) = tokio::join!(worker_loop(ingestion, state, shutdown, FENCE), stop);
+        result.expect("The worker loop failed");
+    }
+
+    async fn rows_from(pool: &PgPool, source: &str) -> i64 {
+        sqlx::query_scalar("SELECT COUNT(*) FROM market_data WHERE source = $1")
+            .bind(source)
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn empty_page_from_a_non_paginating_fallback_keeps_the_sweep_open(pool: PgPool) {
+        let primary = Arc::new(FakeSource::new("primary", listing()));
+        primary.suspend_for(Duration::from_secs(3600));
+        let fallback = Arc::new(FakeSource::new("fallback", vec![vec!["x", "y"]]).not_paginated());
+        let ingestion = ingestion(pool.clone(), vec![primary.clone(), fallback.clone()]);
+        lead(&pool).await;
+
+        run_for(&ingestion, Duration::from_millis(500)).await;
+
+        assert!(primary.requested_pages().is_empty());
+        assert_eq!(fallback.requested_pages(), [1]);
+        assert_eq!(rows_from(&pool, "fallback").await, 2);
+        let active = active_sweeps(&pool).await.unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].next_page, 3);
+        assert_eq!(active[0].pages_fetched, 0);
+        assert!(last_finished_sweeps(&pool).await.unwrap().is_empty());
+    }
+
+    #[sqlx::test]
+    async fn sweep_resumes_on_the_primary_once_it_recovers(pool: PgPool) {
+        let primary = Arc::new(FakeSource::new("primary", listing()));
+        primary.suspend_for(Duration::from_millis(300));
+        let fallback = Arc::new(FakeSource::new("fallback", vec![vec!["x", "y"]]).not_paginated());
+        let ingestion = ingestion(pool.clone(), vec![primary.clone(), fallback.clone()]);
+        lead(&pool).await;
+
+        run_for(&ingestion, Duration::from_millis(1500)).await;
+
+        // Page 3 holds the last coins, and the empty page 4 from the primary ends the listing.
+        assert_eq!(primary.requested_pages(), [3, 4]);
+        // Page 3 was also asked from the fallback, now a secondary, which has nothing there.
+        assert_eq!(fallback.requested_pages(), [1, 3]);
+        assert_eq!(rows_from(&pool, "primary").await, 2);
+        assert!(active_sweeps(&pool).await.unwrap().is_empty());
+        let finished = last_finished_sweeps(&pool).await.unwrap();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].pages_fetched, 1);
+        assert_eq!(finished[0].coins_stored, 2);
+    }
+
+    #[sqlx::test]
+    async fn page_is_rolled_back_once_another_instance_leads(pool: PgPool) {
+        let primary = Arc::new(FakeSource::new("primary", listing()));
+        let ingestion = ingestion(pool.clone(), vec![primary.clone()]);
+        lead(&pool).await;
+        sqlx::query("UPDATE worker_leader SET generation = 2").execute(&pool).await.unwrap();
+
+        run_for(&ingestion, Duration::from_millis(500)).await;
+
+        assert_eq!(primary.requested_pages(), [3]);
+        assert_eq!(rows_from(&pool, "primary").await, 0);
+        let active = active_sweeps(&pool).await.unwrap();
+        assert_eq!(active[0].next_page, 3);
+    }
+
+    #[actix_web::test]
+    async fn only_a_stalled_leader_is_unhealthy() {
+        let state = Arc::new(WorkerState::new("test".to_string(), Duration::from_millis(20)));
+        let status = |state: &Arc<WorkerState>| {
+            let state = actix_web::web::Data::new(state.clone());
+            async move { worker_health_check(state).await.status() }
+        };
+        // A standby follower stays healthy so it is around to take over.
+        assert!(!state.is_leader());
+        assert!(!state.is_stalled());
+        assert_eq!(status(&state).await, StatusCode::OK);
+
+        state.set_leader(true);
+        state.expect_progress_within(Duration::from_millis(30));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(!state.is_stalled());
+        assert_eq!(status(&state).await, StatusCode::OK);
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(state.is_stalled());
+        assert_eq!(status(&state).await, StatusCode::SERVICE_UNAVAILABLE);
+
+        state.set_leader(false);
+        assert!(!state.is_stalled());
+        assert_eq!(status(&state).await, StatusCode::OK);
+    }
+
+    #[sqlx::test]
+    async fn shutdown_abandons_a_page_in_progress(pool: PgPool) {
+        let primary = Arc::new(FakeSource::new("primary", listing()).with_latency(Duration::from_secs(3600)));
+        let ingestion = ingestion(pool.clone(), vec![primary.clone()]);
+        lead(&pool).await;
+
+        let stopped = tokio::time::timeout(Duration::from_secs(5), run_for(&ingestion, Duration::from_millis(300))).await;
+
+        assert!(stopped.is_ok(), "The worker loop ignored the shutdown");
+        assert_eq!(primary.requested_pages(), [3]);
+        assert_eq!(active_sweeps(&pool).await.unwrap()[0].pages_fetched, 0);
+    }
+}
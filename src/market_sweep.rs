@@ -0,0 +1,203 @@
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
+
+/// A single pass over the pages of the markets endpoint that cover a refresh tier.
+/// The row is persisted so a restarted worker resumes from `next_page`.
+#[derive(serde::Serialize, Clone)]
+pub struct Sweep {
+    pub id: i64,
+    pub tier: String,
+    pub next_page: i32,
+    pub pages_fetched: i32,
+    pub coins_stored: i32,
+    pub coins_skipped: i32,
+    /// Coins per page the cursor counts in.
+    pub page_size: Option<i32>,
+    /// Whether the sweep reached the last page of its tier rather than being cut short.
+    pub completed: bool,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+pub struct PageStats {
+    pub coins_stored: i32,
+    pub coins_skipped: i32,
+}
+
+/// Returns the sweep of `tier` in progress, starting a new one from `first_page` when there is none.
+/// A sweep paged by another `page_size` cannot be resumed, so it is closed and a new one started.
+pub async fn current_or_start_sweep(
+    pool: &PgPool,
+    tier: &str,
+    first_page: i32,
+    page_size: u16,
+) -> Result<Sweep, sqlx::Error> {
+    let active = sqlx::query_as!(
+        Sweep,
+        r#"
+            SELECT id, tier, next_page, pages_fetched, coins_stored, coins_skipped, page_size, completed, started_at, finished_at
+            FROM market_sweeps
+            WHERE tier = $1 AND finished_at IS NULL
+            "#,
+        tier,
+    )
+    .fetch_optional(pool)
+    .await?;
+    if let Some(sweep) = active {
+        if sweep.page_size == Some(i32::from(page_size)) {
+            return Ok(sweep);
+        }
+        println!(
+            "Restarting sweep {} of tier {}: it is paged by {:?} coins, the page size is now {}",
+            sweep.id, sweep.tier, sweep.page_size, page_size
+        );
+        finish_sweep(pool, sweep.id, false).await?;
+    }
+    sqlx::query_as!(
+        Sweep,
+        r#"
+            INSERT INTO market_sweeps (tier, next_page, page_size) VALUES ($1, $2, $3)
+            RETURNING id, tier, next_page, pages_fetched, coins_stored, coins_skipped, page_size, completed, started_at, finished_at
+            "#,
+        tier,
+        first_page,
+        i32::from(page_size),
+    )
+    .fetch_one(pool)
+    .await
+}
+
+/// Moves the cursor to the next page and accumulates the page statistics.
+/// Runs inside the page transaction so the cursor never advances past unstored data.
+pub async fn advance_sweep(
+    transaction: &mut Transaction<'_, Postgres>,
+    sweep_id: i64,
+    stats: &PageStats,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            UPDATE market_sweeps SET
+                next_page = next_page + 1,
+                pages_fetched = pages_fetched + 1,
+                coins_stored = coins_stored + $2,
+                coins_skipped = coins_skipped + $3
+            WHERE id = $1
+            "#,
+        sweep_id,
+        stats.coins_stored,
+        stats.coins_skipped,
+    )
+    .execute(transaction)
+    .await?;
+    Ok(())
+}
+
+/// Sweeps in progress, one per tier at most.
+pub async fn active_sweeps(pool: &PgPool) -> Result<Vec<Sweep>, sqlx::Error> {
+    sqlx::query_as!(
+        Sweep,
+        r#"
+            SELECT id, tier, next_page, pages_fetched, coins_stored, coins_skipped, page_size, completed, started_at, finished_at
+            FROM market_sweeps
+            WHERE finished_at IS NULL
+            "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// The most recently finished sweep of every tier.
+pub async fn last_finished_sweeps(pool: &PgPool) -> Result<Vec<Sweep>, sqlx::Error> {
+    sqlx::query_as!(
+        Sweep,
+        r#"
+            SELECT DISTINCT ON (tier) id, tier, next_page, pages_fetched, coins_stored, coins_skipped, page_size, completed, started_at, finished_at
+            FROM market_sweeps
+            WHERE finished_at IS NOT NULL
+            ORDER BY tier, finished_at DESC
+            "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// The most recently finished sweep that reached the last page of its tier, across tiers.
+pub async fn last_completed_sweep(pool: &PgPool) -> Result<Option<Sweep>, sqlx::Error> {
+    sqlx::query_as!(
+        Sweep,
+        r#"
+            SELECT id, tier, next_page, pages_fetched, coins_stored, coins_skipped, page_size, completed, started_at, finished_at
+            FROM market_sweeps
+            WHERE finished_at IS NOT NULL AND completed
+            ORDER BY finished_at DESC
+            LIMIT 1
+            "#,
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Closes the sweep; `completed` when it reached the last page of its tier.
+pub async fn finish_sweep(pool: &PgPool, sweep_id: i64, completed: bool) -> Result<Sweep, sqlx::Error> {
+    sqlx::query_as!(
+        Sweep,
+        r#"
+            UPDATE market_sweeps SET finished_at = CURRENT_TIMESTAMP, completed = $2 WHERE id = $1
+            RETURNING id, tier, next_page, pages_fetched, coins_stored, coins_skipped, page_size, completed, started_at, finished_at
+            "#,
+        sweep_id,
+        completed,
+    )
+    .fetch_one(pool)
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[sqlx::test]
+    async fn sweep_is_resumed_with_the_same_page_size(pool: PgPool) {
+        let sweep = current_or_start_sweep(&pool, "top", 1, 250).await.unwrap();
+
+        let resumed = current_or_start_sweep(&pool, "top", 1, 250).await.unwrap();
+
+        assert_eq!(resumed.id, sweep.id);
+        assert_eq!(resumed.page_size, Some(250));
+    }
+
+    #[sqlx::test]
+    async fn sweep_is_restarted_when_the_page_size_changes(pool: PgPool) {
+        let sweep = current_or_start_sweep(&pool, "mid", 1, 250).await.unwrap();
+        let mut transaction = pool.begin().await.unwrap();
+        advance_sweep(&mut transaction, sweep.id, &PageStats::default()).await.unwrap();
+        transaction.commit().await.unwrap();
+
+        // Page 2 of 100 coins would skip ranks 101 to 250 of the old paging.
+        let restarted = current_or_start_sweep(&pool, "mid", 2, 100).await.unwrap();
+
+        assert_ne!(restarted.id, sweep.id);
+        assert_eq!(restarted.next_page, 2);
+        assert_eq!(restarted.page_size, Some(100));
+        let finished = last_finished_sweeps(&pool).await.unwrap();
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].id, sweep.id);
+        assert_eq!(finished[0].next_page, 2);
+        assert!(!finished[0].completed);
+    }
+
+    #[sqlx::test]
+    async fn only_completed_sweeps_count_as_successful(pool: PgPool) {
+        let completed = current_or_start_sweep(&pool, "top", 1, 250).await.unwrap();
+        finish_sweep(&pool, completed.id, true).await.unwrap();
+        let cut_short = current_or_start_sweep(&pool, "mid", 1, 250).await.unwrap();
+        finish_sweep(&pool, cut_short.id, false).await.unwrap();
+
+        let last = last_completed_sweep(&pool).await.unwrap().unwrap();
+
+        assert_eq!(last.id, completed.id);
+        assert!(last.completed);
+        assert_eq!(last_finished_sweeps(&pool).await.unwrap().len(), 2);
+    }
+}
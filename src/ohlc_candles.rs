@@ -0,0 +1,45 @@
+use std::collections::BTreeMap;
+
+use sqlx::PgPool;
+
+use crate::domains::{Candle, CandleInterval, Currency};
+
+/// Upserts the candles of a coin; the latest candle is still moving until it closes.
+pub async fn store_candles(
+    pool: &PgPool,
+    coin_id: &str,
+    currency: &Currency,
+    interval: CandleInterval,
+    source: &str,
+    candles: &[Candle],
+) -> Result<(), sqlx::Error> {
+    // A candle listed twice would hit the same row twice within the upsert.
+    let candles: BTreeMap<_, _> = candles.iter().map(|c| (c.close_time, c)).collect();
+    let candles: Vec<&Candle> = candles.into_values().collect();
+    sqlx::query!(
+        r#"
+            INSERT INTO ohlc_candles (coin_id, currency, interval, close_time, open, high, low, close, source)
+            SELECT $1, $2, $3, *, $9
+            FROM UNNEST($4::timestamptz[], $5::float8[], $6::float8[], $7::float8[], $8::float8[])
+            ON CONFLICT (coin_id, currency, interval, close_time) DO UPDATE SET
+                open = EXCLUDED.open,
+                high = EXCLUDED.high,
+                low = EXCLUDED.low,
+                close = EXCLUDED.close,
+                source = EXCLUDED.source,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        coin_id,
+        currency.as_str(),
+        interval.as_str(),
+        &candles.iter().map(|c| c.close_time).collect::<Vec<_>>(),
+        &candles.iter().map(|c| c.open).collect::<Vec<_>>(),
+        &candles.iter().map(|c| c.high).collect::<Vec<_>>(),
+        &candles.iter().map(|c| c.low).collect::<Vec<_>>(),
+        &candles.iter().map(|c| c.close).collect::<Vec<_>>(),
+        source,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
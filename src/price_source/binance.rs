@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{TimeZone, Utc};
+
+use super::{MarketPage, PriceSource, PriceSourceError};
+use crate::{
+    binance_client::{BinanceClient, Ticker24h},
+    domains::{Currency, MarketData, Quote},
+};
+
+/// How long the list of tradable pairs from `exchangeInfo` is reused.
+const PAIRS_TTL: Duration = Duration::from_secs(3600);
+
+/// Binance spot prices for the coins listed in `assets`.
+/// Binance has no notion of coin ids, so every base asset we track is mapped
+/// explicitly to the CoinGecko id it is stored under.
+pub struct BinanceSource {
+    client: BinanceClient,
+    /// Lowercase base asset (`btc`) to coin id (`bitcoin`).
+    assets: HashMap<String, String>,
+    trading_pairs: Mutex<Option<(Instant, Arc<HashSet<String>>)>>,
+}
+
+impl BinanceSource {
+    pub fn new(client: BinanceClient, assets: HashMap<String, String>) -> Self {
+        let assets = assets
+            .into_iter()
+            .map(|(asset, id)| (asset.to_lowercase(), id))
+            .collect();
+        Self {
+            client,
+            assets,
+            trading_pairs: Mutex::new(None),
+        }
+    }
+
+    /// Binance quote asset used to price coins in `currency`.
+    fn quote_asset(currency: &Currency) -> Option<&'static str> {
+        match currency {
+            Currency::USD => Some("USDT"),
+            Currency::EUR => Some("EUR"),
+            Currency::GBP => Some("GBP"),
+            Currency::TRY => Some("TRY"),
+            Currency::BRL => Some("BRL"),
+            Currency::AUD => Some("AUD"),
+            Currency::ARS => Some("ARS"),
+            Currency::ZAR => Some("ZAR"),
+            Currency::PLN => Some("PLN"),
+            Currency::UAH => Some("UAH"),
+            Currency::JPY => Some("JPY"),
+            Currency::BTC => Some("BTC"),
+            Currency::ETH => Some("ETH"),
+            Currency::BNB => Some("BNB"),
+            _ => None,
+        }
+    }
+
+    async fn trading_pairs(&self) -> Result<Arc<HashSet<String>>, PriceSourceError> {
+        if let Some((fetched_at, pairs)) = self.trading_pairs.lock().unwrap().as_ref() {
+            if fetched_at.elapsed() < PAIRS_TTL {
+                return Ok(pairs.clone());
+            }
+        }
+        let info = self
+            .client
+            .exchange_info()
+            .await?;
+        let pairs: Arc<HashSet<String>> = Arc::new(
+            info.symbols
+                .into_iter()
+                .filter(|s| s.status == "TRADING")
+                .map(|s| s.symbol)
+                .collect(),
+        );
+        *self.trading_pairs.lock().unwrap() = Some((Instant::now(), pairs.clone()));
+        Ok(pairs)
+    }
+
+    /// Fetches tickers for the given base assets and pairs each with its coin id.
+    async fn tickers(
+        &self,
+        assets: Vec<&String>,
+        currency: &Currency,
+    ) -> Result<Vec<(String, String, Ticker24h)>, PriceSourceError> {
+        let quote_asset = Self::quote_asset(currency)
+            .ok_or_else(|| PriceSourceError::UnsupportedCurrency(currency.as_str().to_string()))?;
+        let pairs = self.trading_pairs().await?;
+        let mut by_symbol: HashMap<String, (String, String)> = assets
+            .into_iter()
+            .map(|asset| (format!("{}{}", asset.to_uppercase(), quote_asset), asset))
+            .filter(|(symbol, _)| pairs.contains(symbol))
+            .map(|(symbol, asset)| (symbol, (self.assets[asset].clone(), asset.clone())))
+            .collect();
+        if by_symbol.is_empty() {
+            return Ok(vec![]);
+        }
+        let symbols: Vec<String> = by_symbol.keys().cloned().collect();
+        let tickers = self
+            .client
+            .ticker_24h(&symbols)
+            .await?;
+        Ok(tickers
+            .into_iter()
+            .filter_map(|ticker| {
+                let (id, asset) = by_symbol.remove(&ticker.symbol)?;
+                Some((id, asset, ticker))
+            })
+            .collect())
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for BinanceSource {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    fn supported_currencies(&self) -> Vec<Currency> {
+        Currency::ALL
+            .into_iter()
+            .filter(|c| Self::quote_asset(c).is_some())
+            .collect()
+    }
+
+    /// Binance is not paginated: every tracked asset comes back on page 1,
+    /// whatever the page size and order.
+    fn paginates(&self) -> bool {
+        false
+    }
+
+    async fn list_markets(
+        &self,
+        currency: &Currency,
+        page: &MarketPage,
+    ) -> Result<Vec<MarketData>, PriceSourceError> {
+        if page.page > 1 {
+            return Ok(vec![]);
+        }
+        let tickers = self.tickers(self.assets.keys().collect(), currency).await?;
+        Ok(tickers
+            .into_iter()
+            .map(|(id, asset, ticker)| MarketData {
+                id: Some(id),
+                symbol: Some(asset),
+                current_price: Some(ticker.last_price),
+                total_volume: Some(ticker.quote_volume),
+                high_24h: Some(ticker.high_price),
+                low_24h: Some(ticker.low_price),
+                price_change_24h: Some(ticker.price_change),
+                price_change_percentage_24h: Some(ticker.price_change_percent),
+                price_change_percentage_24h_in_currency: Some(ticker.price_change_percent),
+                last_updated: Utc
+                    .timestamp_millis_opt(ticker.close_time)
+                    .single()
+                    .map(|ts| ts.to_rfc3339()),
+                ..MarketData::default()
+            })
+            .collect())
+    }
+
+    async fn fetch_quotes(
+        &self,
+        ids: &[String],
+        currency: &Currency,
+    ) -> Result<Vec<Quote>, PriceSourceError> {
+        let assets = self
+            .assets
+            .iter()
+            .filter(|(_, id)| ids.contains(id))
+            .map(|(asset, _)| asset)
+            .collect();
+        let tickers = self.tickers(assets, currency).await?;
+        Ok(tickers
+            .into_iter()
+            .map(|(id, _, ticker)| Quote {
+                coin_id: id,
+                currency: *currency,
+                price: ticker.last_price,
+                volume_24h: Some(ticker.quote_volume),
+                last_updated: Utc
+                    .timestamp_millis_opt(ticker.close_time)
+                    .single()
+                    .unwrap_or_else(Utc::now),
+                source: self.name().to_string(),
+            })
+            .collect())
+    }
+
+    fn suspended_for(&self) -> Option<Duration> {
+        self.client.suspended_for()
+    }
+}
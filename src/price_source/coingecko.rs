@@ -0,0 +1,223 @@
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+
+use super::{MarketPage, PriceSource, PriceSourceError};
+use crate::{
+    domains::{Candle, CandleInterval, Coin, ContractAddress, Currency, ExchangeRate, MarketData, Quote, TokenQuote},
+    gecko_client::{
+        CoinsListRequest, GeckoClient, MarketsRequest, OhlcRequest, SimplePriceRequest,
+        TokenPriceRequest,
+    },
+};
+
+/// Windows requested as `price_change_percentage_<window>_in_currency`.
+const PRICE_CHANGE_WINDOWS: [&str; 7] = ["1h", "24h", "7d", "14d", "30d", "200d", "1y"];
+
+/// `days` and `interval` of the `coins/{id}/ohlc` call returning candles of `interval`.
+/// Without `interval` the candle size follows from `days`: 30 minutes up to 2 days,
+/// 4 hours up to 30 days and 4 days beyond. Hourly and daily candles need a paid plan.
+fn ohlc_range(interval: CandleInterval) -> (&'static str, Option<&'static str>) {
+    match interval {
+        CandleInterval::M30 => ("1", None),
+        CandleInterval::H1 => ("30", Some("hourly")),
+        CandleInterval::H4 => ("30", None),
+        CandleInterval::D1 => ("180", Some("daily")),
+        CandleInterval::D4 => ("365", None),
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for GeckoClient {
+    fn name(&self) -> &'static str {
+        "coingecko"
+    }
+
+    fn supported_currencies(&self) -> Vec<Currency> {
+        Currency::ALL.to_vec()
+    }
+
+    async fn list_markets(
+        &self,
+        currency: &Currency,
+        page: &MarketPage,
+    ) -> Result<Vec<MarketData>, PriceSourceError> {
+        let request = MarketsRequest {
+            order: Some(page.order),
+            per_page: Some(page.per_page),
+            page: Some(page.page),
+            sparkline: page.sparkline,
+            price_change_percentage: PRICE_CHANGE_WINDOWS.iter().map(|w| w.to_string()).collect(),
+            ..MarketsRequest::new(*currency)
+        };
+        let result = self.markets(&request).await?;
+        Ok(result.into_iter().flatten().collect())
+    }
+
+    async fn fetch_quotes(
+        &self,
+        ids: &[String],
+        currency: &Currency,
+    ) -> Result<Vec<Quote>, PriceSourceError> {
+        if ids.is_empty() {
+            return Ok(vec![]);
+        }
+        let vs = currency.as_str();
+        let request = SimplePriceRequest {
+            ids: ids.to_vec(),
+            vs_currencies: vec![*currency],
+            include_24hr_vol: true,
+            include_last_updated_at: true,
+            ..SimplePriceRequest::default()
+        };
+        let result = self.simple_price(&request).await?;
+        let volume_key = format!("{}_24h_vol", vs);
+        let quotes = result
+            .into_iter()
+            .filter_map(|(coin_id, fields)| {
+                let price = fields.get(vs).copied().flatten()?;
+                let last_updated = fields
+                    .get("last_updated_at")
+                    .copied()
+                    .flatten()
+                    .and_then(|ts| Utc.timestamp_opt(ts as i64, 0).single())
+                    .unwrap_or_else(Utc::now);
+                Some(Quote {
+                    coin_id,
+                    currency: *currency,
+                    price,
+                    volume_24h: fields.get(&volume_key).copied().flatten(),
+                    last_updated,
+                    source: self.name().to_string(),
+                })
+            })
+            .collect();
+        Ok(quotes)
+    }
+
+    async fn fetch_token_quote(
+        &self,
+        platform: &str,
+        contract_address: &ContractAddress,
+        currency: &Currency,
+    ) -> Result<Option<TokenQuote>, PriceSourceError> {
+        let vs = currency.as_str();
+        let request = TokenPriceRequest {
+            platform: platform.to_string(),
+            contract_addresses: vec![contract_address.as_str().to_string()],
+            vs_currencies: vec![*currency],
+            include_market_cap: true,
+            include_24hr_vol: true,
+            include_24hr_change: true,
+            include_last_updated_at: true,
+        };
+        let result = self.token_price(&request).await?;
+        let fields = result
+            .into_iter()
+            .find(|(address, _)| address.eq_ignore_ascii_case(contract_address.as_str()))
+            .map(|(_, fields)| fields);
+        let Some(fields) = fields else {
+            return Ok(None);
+        };
+        let field = |suffix: &str| fields.get(&format!("{}{}", vs, suffix)).copied().flatten();
+        let Some(price) = field("") else {
+            return Ok(None);
+        };
+        let last_updated = fields
+            .get("last_updated_at")
+            .copied()
+            .flatten()
+            .and_then(|ts| Utc.timestamp_opt(ts as i64, 0).single())
+            .unwrap_or_else(Utc::now);
+        Ok(Some(TokenQuote {
+            platform: platform.to_string(),
+            contract_address: contract_address.checksummed(),
+            currency: *currency,
+            price,
+            market_cap: field("_market_cap"),
+            volume_24h: field("_24h_vol"),
+            change_percentage_24h: field("_24h_change"),
+            last_updated,
+            source: self.name().to_string(),
+        }))
+    }
+
+    async fn fetch_ohlc(
+        &self,
+        id: &str,
+        currency: &Currency,
+        interval: CandleInterval,
+    ) -> Result<Option<Vec<Candle>>, PriceSourceError> {
+        let (days, gecko_interval) = ohlc_range(interval);
+        let request = OhlcRequest {
+            id: id.to_string(),
+            vs_currency: *currency,
+            days: days.to_string(),
+            interval: gecko_interval.map(String::from),
+        };
+        let candles = self
+            .ohlc(&request)
+            .await?
+            .into_iter()
+            .filter_map(|ohlc| {
+                Some(Candle {
+                    close_time: Utc.timestamp_millis_opt(ohlc.0 as i64).single()?,
+                    open: ohlc.1,
+                    high: ohlc.2,
+                    low: ohlc.3,
+                    close: ohlc.4,
+                })
+            })
+            .collect();
+        Ok(Some(candles))
+    }
+
+    async fn list_coins(&self) -> Result<Option<Vec<Coin>>, PriceSourceError> {
+        let entries = self
+            .coins_list(&CoinsListRequest { include_platform: true })
+            .await?;
+        let coins = entries
+            .into_iter()
+            .map(|entry| Coin {
+                id: entry.id,
+                symbol: entry.symbol,
+                name: entry.name,
+                // Coins without a contract come back as `{"": ""}` or with null addresses.
+                platforms: entry
+                    .platforms
+                    .into_iter()
+                    .filter_map(|(platform, address)| match address {
+                        Some(address) if !platform.is_empty() && !address.is_empty() => {
+                            Some((platform, address))
+                        }
+                        _ => None,
+                    })
+                    .collect(),
+            })
+            .collect();
+        Ok(Some(coins))
+    }
+
+    async fn fetch_exchange_rates(&self) -> Result<Option<Vec<ExchangeRate>>, PriceSourceError> {
+        let result = self.exchange_rates().await?;
+        // Units such as `sats` or `bits` that are not a `Currency` are left out.
+        let rates = result
+            .rates
+            .into_iter()
+            .filter_map(|(code, rate)| {
+                Some(ExchangeRate {
+                    currency: Currency::try_from(code).ok()?,
+                    name: rate.name,
+                    unit: rate.unit,
+                    rate_type: rate.rate_type,
+                    value: rate.value,
+                })
+            })
+            .collect();
+        Ok(Some(rates))
+    }
+
+    fn suspended_for(&self) -> Option<Duration> {
+        GeckoClient::suspended_for(self)
+    }
+}
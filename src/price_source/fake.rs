@@ -0,0 +1,101 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::{MarketPage, PriceSource, PriceSourceError};
+use crate::domains::{Currency, MarketData, Quote};
+
+/// An in-memory provider serving a fixed listing, for driving the worker in tests.
+pub struct FakeSource {
+    name: &'static str,
+    paginates: bool,
+    /// Coin ids of every page, page 1 first; later pages are empty.
+    pages: Vec<Vec<&'static str>>,
+    /// How long every page takes to come back.
+    latency: Duration,
+    suspended_until: Mutex<Option<Instant>>,
+    requested_pages: Mutex<Vec<u16>>,
+}
+
+impl FakeSource {
+    pub fn new(name: &'static str, pages: Vec<Vec<&'static str>>) -> Self {
+        Self {
+            name,
+            paginates: true,
+            pages,
+            latency: Duration::ZERO,
+            suspended_until: Mutex::new(None),
+            requested_pages: Mutex::new(vec![]),
+        }
+    }
+
+    /// Lists every coin on page 1 and nothing after, like Binance.
+    pub fn not_paginated(self) -> Self {
+        Self { paginates: false, ..self }
+    }
+
+    pub fn with_latency(self, latency: Duration) -> Self {
+        Self { latency, ..self }
+    }
+
+    pub fn suspend_for(&self, duration: Duration) {
+        *self.suspended_until.lock().unwrap() = Some(Instant::now() + duration);
+    }
+
+    /// Pages asked for through `list_markets`, in order.
+    pub fn requested_pages(&self) -> Vec<u16> {
+        self.requested_pages.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl PriceSource for FakeSource {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn supported_currencies(&self) -> Vec<Currency> {
+        vec![Currency::USD]
+    }
+
+    fn paginates(&self) -> bool {
+        self.paginates
+    }
+
+    async fn list_markets(
+        &self,
+        _currency: &Currency,
+        page: &MarketPage,
+    ) -> Result<Vec<MarketData>, PriceSourceError> {
+        self.requested_pages.lock().unwrap().push(page.page);
+        tokio::time::sleep(self.latency).await;
+        if !self.paginates && page.page > 1 {
+            return Ok(vec![]);
+        }
+        let ids = self.pages.get(usize::from(page.page) - 1).cloned().unwrap_or_default();
+        Ok(ids
+            .into_iter()
+            .map(|id| MarketData {
+                id: Some(id.to_string()),
+                symbol: Some(id.to_string()),
+                current_price: Some(1.0),
+                ..MarketData::default()
+            })
+            .collect())
+    }
+
+    async fn fetch_quotes(
+        &self,
+        _ids: &[String],
+        _currency: &Currency,
+    ) -> Result<Vec<Quote>, PriceSourceError> {
+        Ok(vec![])
+    }
+
+    fn suspended_for(&self) -> Option<Duration> {
+        self.suspended_until
+            .lock()
+            .unwrap()
+            .map(|until| until.saturating_duration_since(Instant::now()))
+            .filter(|left| !left.is_zero())
+    }
+}
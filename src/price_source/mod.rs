@@ -0,0 +1,91 @@
+mod binance;
+mod coingecko;
+#[cfg(test)]
+pub mod fake;
+mod price_source_error;
+pub use binance::BinanceSource;
+pub use price_source_error::PriceSourceError;
+
+use std::time::Duration;
+
+use crate::{
+    domains::{Candle, CandleInterval, Coin, ContractAddress, Currency, ExchangeRate, MarketData, Quote, TokenQuote},
+    gecko_client::MarketOrder,
+};
+
+/// The slice of the market listing requested from `list_markets`.
+#[derive(Clone, Copy, Debug)]
+pub struct MarketPage {
+    pub page: u16,
+    pub per_page: u16,
+    pub order: MarketOrder,
+    /// Whether to include the 7 day sparkline of every coin.
+    pub sparkline: bool,
+}
+
+/// An upstream provider of market data.
+/// The worker and the routes only talk to providers through this trait.
+#[async_trait::async_trait]
+pub trait PriceSource: Send + Sync {
+    /// Short identifier stored next to the data this source produced.
+    fn name(&self) -> &'static str;
+
+    fn supported_currencies(&self) -> Vec<Currency>;
+
+    /// Whether `list_markets` actually pages through the whole listing. A source that
+    /// does not returns everything it has on page 1 and nothing after.
+    fn paginates(&self) -> bool {
+        true
+    }
+
+    /// One page of coins in the requested order. From a source that paginates,
+    /// an empty page means the listing is exhausted.
+    async fn list_markets(
+        &self,
+        currency: &Currency,
+        page: &MarketPage,
+    ) -> Result<Vec<MarketData>, PriceSourceError>;
+
+    /// Latest prices of the given coin ids. Unknown ids are left out of the result.
+    async fn fetch_quotes(
+        &self,
+        ids: &[String],
+        currency: &Currency,
+    ) -> Result<Vec<Quote>, PriceSourceError>;
+
+    /// Latest price of the token at `contract_address` on `platform`, or `None` when
+    /// the source does not know the token or cannot price tokens by address.
+    async fn fetch_token_quote(
+        &self,
+        _platform: &str,
+        _contract_address: &ContractAddress,
+        _currency: &Currency,
+    ) -> Result<Option<TokenQuote>, PriceSourceError> {
+        Ok(None)
+    }
+
+    /// Recent OHLC candles of a coin, oldest first, or `None` when the source has no candles.
+    async fn fetch_ohlc(
+        &self,
+        _id: &str,
+        _currency: &Currency,
+        _interval: CandleInterval,
+    ) -> Result<Option<Vec<Candle>>, PriceSourceError> {
+        Ok(None)
+    }
+
+    /// Every coin the source knows of, or `None` when it keeps no coin registry.
+    async fn list_coins(&self) -> Result<Option<Vec<Coin>>, PriceSourceError> {
+        Ok(None)
+    }
+
+    /// What one BTC is worth in every supported currency, or `None` when the source has no rates.
+    async fn fetch_exchange_rates(&self) -> Result<Option<Vec<ExchangeRate>>, PriceSourceError> {
+        Ok(None)
+    }
+
+    /// Time left before the source accepts calls again, if it is backing off.
+    fn suspended_for(&self) -> Option<Duration> {
+        None
+    }
+}
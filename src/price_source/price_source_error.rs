@@ -0,0 +1,17 @@
+use crate::{binance_client::BinanceError, gecko_client::GeckoError, utils::error_chain_fmt};
+
+#[derive(thiserror::Error)]
+pub enum PriceSourceError {
+    #[error("{0} is not supported by this price source")]
+    UnsupportedCurrency(String),
+    #[error(transparent)]
+    GeckoError(#[from] GeckoError),
+    #[error(transparent)]
+    BinanceError(#[from] BinanceError),
+}
+
+impl std::fmt::Debug for PriceSourceError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
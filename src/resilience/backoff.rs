@@ -0,0 +1,61 @@
+use rand::Rng;
+use std::time::Duration;
+
+/// Exponential backoff with full jitter.
+pub struct Backoff {
+    pub base: Duration,
+    pub max: Duration,
+    pub max_retries: u32,
+}
+
+impl Backoff {
+    /// Random delay in `[0, min(max, base * 2^attempt)]`.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self
+            .base
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max);
+        let millis = rand::thread_rng().gen_range(0..=ceiling.as_millis() as u64);
+        Duration::from_millis(millis)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn backoff() -> Backoff {
+        Backoff {
+            base: Duration::from_millis(100),
+            max: Duration::from_millis(1000),
+            max_retries: 3,
+        }
+    }
+
+    #[test]
+    fn delay_stays_under_the_exponential_ceiling() {
+        let backoff = backoff();
+        for attempt in 0..4 {
+            let ceiling = Duration::from_millis(100 * 2u64.pow(attempt));
+            for _ in 0..100 {
+                assert!(backoff.delay(attempt) <= ceiling);
+            }
+        }
+    }
+
+    #[test]
+    fn delay_is_capped_at_max() {
+        let backoff = backoff();
+        for attempt in [4, 10, 31, 32, u32::MAX] {
+            for _ in 0..100 {
+                assert!(backoff.delay(attempt) <= backoff.max);
+            }
+        }
+    }
+
+    #[test]
+    fn zero_base_never_waits() {
+        let backoff = Backoff { base: Duration::ZERO, ..backoff() };
+        assert_eq!(backoff.delay(5), Duration::ZERO);
+    }
+}
@@ -0,0 +1,180 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// Stops calls to an upstream after `threshold` consecutive failures.
+/// Once `cooldown` has elapsed a single trial call is let through while the others
+/// keep being rejected; a success closes the circuit again, a failure keeps it open
+/// for another cooldown.
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+    /// When the trial call of the half-open circuit was let through.
+    probe_started_at: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState {
+                consecutive_failures: 0,
+                open_until: None,
+                probe_started_at: None,
+            }),
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().unwrap();
+        match state.open_until {
+            None => CircuitState::Closed,
+            Some(open_until) if Instant::now() < open_until => CircuitState::Open,
+            Some(_) => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Whether a call may go through. In the half-open state only the first caller
+    /// gets through; a trial call that never reports back is replaced after a cooldown.
+    pub fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        match state.open_until {
+            None => true,
+            Some(open_until) if Instant::now() < open_until => false,
+            Some(_) => {
+                if state.probe_started_at.is_some_and(|started| started.elapsed() < self.cooldown) {
+                    return false;
+                }
+                state.probe_started_at = Some(Instant::now());
+                true
+            }
+        }
+    }
+
+    /// Time left before a trial call is allowed, if the circuit is open.
+    pub fn open_for(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        state
+            .open_until
+            .map(|open_until| open_until.saturating_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.open_until = None;
+        state.probe_started_at = None;
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.threshold {
+            state.open_until = Some(Instant::now() + self.cooldown);
+            state.probe_started_at = None;
+        }
+    }
+
+    /// Opens the circuit for `duration` whatever the failure count, e.g. when
+    /// the upstream asks to be left alone for longer than a retry may wait.
+    pub fn trip(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.open_until = Some(Instant::now() + duration);
+        state.probe_started_at = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COOLDOWN: Duration = Duration::from_millis(50);
+
+    #[test]
+    fn opens_after_threshold_consecutive_failures() {
+        let breaker = CircuitBreaker::new(2, COOLDOWN);
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.allow());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.allow());
+        assert!(breaker.open_for().is_some_and(|left| left <= COOLDOWN));
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let breaker = CircuitBreaker::new(2, COOLDOWN);
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn half_open_lets_a_single_trial_call_through() {
+        let breaker = CircuitBreaker::new(1, COOLDOWN);
+        breaker.record_failure();
+        std::thread::sleep(COOLDOWN);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert_eq!(breaker.open_for(), None);
+        assert!(breaker.allow());
+        assert!(!breaker.allow());
+        assert!(!breaker.allow());
+    }
+
+    #[test]
+    fn successful_trial_call_closes_the_circuit() {
+        let breaker = CircuitBreaker::new(1, COOLDOWN);
+        breaker.record_failure();
+        std::thread::sleep(COOLDOWN);
+        assert!(breaker.allow());
+        breaker.record_success();
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.allow());
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn failed_trial_call_reopens_the_circuit() {
+        let breaker = CircuitBreaker::new(1, COOLDOWN);
+        breaker.record_failure();
+        std::thread::sleep(COOLDOWN);
+        assert!(breaker.allow());
+        breaker.record_failure();
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.allow());
+    }
+
+    #[test]
+    fn lost_trial_call_is_replaced_after_a_cooldown() {
+        let breaker = CircuitBreaker::new(1, COOLDOWN);
+        breaker.record_failure();
+        std::thread::sleep(COOLDOWN);
+        assert!(breaker.allow());
+        std::thread::sleep(COOLDOWN);
+        assert!(breaker.allow());
+    }
+
+    #[test]
+    fn trip_opens_the_circuit_for_the_given_duration() {
+        let breaker = CircuitBreaker::new(5, COOLDOWN);
+        breaker.trip(Duration::from_secs(60));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.open_for().is_some_and(|left| left > COOLDOWN));
+    }
+}
@@ -0,0 +1,8 @@
+mod backoff;
+mod circuit_breaker;
+mod retry_after;
+mod token_bucket;
+pub use backoff::Backoff;
+pub use circuit_breaker::{CircuitBreaker, CircuitState};
+pub use retry_after::retry_after;
+pub use token_bucket::TokenBucket;
@@ -0,0 +1,101 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Request budget shared by every caller of a client.
+/// Holds up to `capacity` tokens and refills `refill_per_minute` tokens per minute.
+pub struct TokenBucket {
+    capacity: f64,
+    refill_per_second: f64,
+    state: Mutex<BucketState>,
+}
+
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, refill_per_minute: u32) -> Self {
+        let capacity = f64::from(capacity.max(1));
+        Self {
+            capacity,
+            refill_per_second: f64::from(refill_per_minute.max(1)) / 60.0,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Waits until a token is available and takes it.
+    pub async fn acquire(&self) {
+        loop {
+            let wait = match self.try_acquire() {
+                Ok(()) => return,
+                Err(wait) => wait,
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Takes a token if one is available, otherwise returns how long until the next one.
+    pub fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        state.last_refill = now;
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - state.tokens) / self.refill_per_second,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burst_is_limited_to_the_capacity() {
+        let bucket = TokenBucket::new(3, 60);
+        for _ in 0..3 {
+            assert!(bucket.try_acquire().is_ok());
+        }
+        let wait = bucket.try_acquire().unwrap_err();
+        assert!(wait <= Duration::from_secs(1));
+        assert!(wait > Duration::from_millis(900));
+    }
+
+    #[test]
+    fn tokens_refill_over_time() {
+        // 100 tokens per second.
+        let bucket = TokenBucket::new(1, 6000);
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_err());
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(bucket.try_acquire().is_ok());
+    }
+
+    #[test]
+    fn refill_never_exceeds_the_capacity() {
+        let bucket = TokenBucket::new(2, 6000);
+        std::thread::sleep(Duration::from_millis(50));
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_ok());
+        assert!(bucket.try_acquire().is_err());
+    }
+
+    #[tokio::test]
+    async fn acquire_waits_for_the_next_token() {
+        let bucket = TokenBucket::new(1, 6000);
+        bucket.acquire().await;
+        let started = Instant::now();
+        bucket.acquire().await;
+        assert!(started.elapsed() >= Duration::from_millis(5));
+    }
+}
@@ -1,6 +1,9 @@
 use actix_web::{HttpResponse, ResponseError, http::StatusCode};
 use serde::Serialize;
-use crate::utils::error_chain_fmt;
+use crate::{
+    binance_client::BinanceError, gecko_client::GeckoError, price_source::PriceSourceError,
+    utils::error_chain_fmt,
+};
 
 #[derive(thiserror::Error)]
 pub enum CoinFetchError {
@@ -8,8 +11,10 @@ pub enum CoinFetchError {
     ValidationError(String),
     #[error("{0}")]
     NotFoundError(String),
-    #[error("Failed to fetch result from CoinGecko")]
-    GeckoError(#[from] reqwest::Error),
+    #[error("{0}")]
+    Unavailable(String),
+    #[error("Failed to fetch result from the price source")]
+    SourceError(#[from] PriceSourceError),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error)
 }
@@ -24,7 +29,14 @@ impl ResponseError for CoinFetchError {
     fn status_code(&self) -> StatusCode {
         match self {
             Self::ValidationError(_) => StatusCode::BAD_REQUEST,
-            Self::GeckoError(_) |
+            Self::SourceError(PriceSourceError::GeckoError(
+                GeckoError::RateLimited(_) | GeckoError::CircuitOpen,
+            ))
+            | Self::SourceError(PriceSourceError::BinanceError(
+                BinanceError::RateLimited(_) | BinanceError::CircuitOpen,
+            ))
+            | Self::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::SourceError(_) |
             Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Self::NotFoundError(_) => StatusCode::NOT_FOUND,
         }
@@ -0,0 +1,93 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use super::CoinFetchError;
+use crate::{domains::Currency, exchange_rates::conversion_rate, startup::MarketDefaults};
+
+#[derive(serde::Deserialize, Debug)]
+pub struct ConvertQuery {
+    from: String,
+    to: String,
+    amount: Option<f64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct ConvertResponse {
+    pub from: String,
+    pub to: String,
+    pub amount: f64,
+    pub result: f64,
+    /// Units of `to` one unit of `from` is worth.
+    pub rate: f64,
+    /// When the oldest price or exchange rate used was stored.
+    pub updated_at: DateTime<Utc>,
+}
+
+/// USD value of one unit of a currency or coin, and when it was stored.
+struct UsdValue {
+    value: f64,
+    updated_at: DateTime<Utc>,
+}
+
+/// Converts `amount` (1 by default) between any two of currency codes and coin ids,
+/// going through USD: coins by their stored USD price, currencies by the exchange rates.
+pub async fn convert_amount(
+    query: web::Query<ConvertQuery>,
+    pool: web::Data<PgPool>,
+    defaults: web::Data<MarketDefaults>,
+) -> Result<HttpResponse, CoinFetchError> {
+    let ConvertQuery { from, to, amount } = query.into_inner();
+    let amount = amount.unwrap_or(1.0);
+    if !amount.is_finite() || amount < 0.0 {
+        return Err(CoinFetchError::ValidationError(
+            "`amount` must not be negative".into(),
+        ));
+    }
+    let from = from.to_lowercase();
+    let to = to.to_lowercase();
+    let from_value = usd_value(pool.as_ref(), &from, &defaults.source).await?;
+    let to_value = usd_value(pool.as_ref(), &to, &defaults.source).await?;
+    // A `to` worth nothing, or a corrupt price, has no rate to divide by.
+    if !to_value.value.is_finite() || to_value.value <= 0.0 {
+        return Err(CoinFetchError::NotFoundError(format!("Price of {} in usd not found !", to)));
+    }
+    let rate = from_value.value / to_value.value;
+    Ok(HttpResponse::Ok().json(ConvertResponse {
+        from,
+        to,
+        amount,
+        result: amount * rate,
+        rate,
+        updated_at: from_value.updated_at.min(to_value.updated_at),
+    }))
+}
+
+/// `asset` is read as a currency code first, so `btc` is the currency and `bitcoin` the coin.
+/// The price of the default source is preferred for coins.
+async fn usd_value(pool: &PgPool, asset: &str, source: &str) -> Result<UsdValue, CoinFetchError> {
+    let not_found = || CoinFetchError::NotFoundError(format!("Price of {} in usd not found !", asset));
+    if let Ok(currency) = Currency::try_from(asset.to_string()) {
+        let conversion = conversion_rate(pool, &currency, &Currency::USD)
+            .await
+            .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?
+            .ok_or_else(not_found)?;
+        return Ok(UsdValue { value: conversion.rate, updated_at: conversion.updated_at });
+    }
+    let row = sqlx::query!(
+        r#"
+            SELECT current_price AS "current_price!", updated_at
+            FROM market_data
+            WHERE id = $1 AND currency = 'usd' AND current_price > 0
+            ORDER BY source = $2 DESC, updated_at DESC
+            LIMIT 1
+            "#,
+        asset,
+        source,
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?
+    .ok_or_else(not_found)?;
+    Ok(UsdValue { value: row.current_price, updated_at: row.updated_at })
+}
@@ -0,0 +1,102 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::PgPool;
+
+use super::CoinFetchError;
+use crate::{domains::Currency, startup::MarketDefaults};
+
+#[derive(serde::Deserialize, Debug)]
+pub struct HistoryPath {
+    id: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct HistoryQuery {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    vs: Option<String>,
+    source: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct HistorySnapshot {
+    pub recorded_at: DateTime<Utc>,
+    pub current_price: Option<f64>,
+    pub market_cap: Option<f64>,
+    pub market_cap_rank: Option<i32>,
+    pub total_volume: Option<f64>,
+    pub price_change_percentage_24h: Option<f64>,
+    pub last_updated: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct HistoryResponse {
+    pub id: String,
+    pub vs: String,
+    pub source: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub snapshots: Vec<HistorySnapshot>,
+}
+
+/// Returns the stored snapshots of a coin between `from` and `to`.
+/// Defaults to the last 24 hours in the default currency when the query is left empty.
+pub async fn get_coin_history(
+    path: web::Path<HistoryPath>,
+    query: web::Query<HistoryQuery>,
+    pool: web::Data<PgPool>,
+    defaults: web::Data<MarketDefaults>,
+) -> Result<HttpResponse, CoinFetchError> {
+    let id = path.into_inner().id;
+    let query = query.into_inner();
+    let currency = match query.vs {
+        Some(vs) => Currency::try_from(vs).map_err(CoinFetchError::ValidationError)?,
+        None => defaults.currency,
+    };
+    let source = query.source.unwrap_or_else(|| defaults.source.clone());
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::hours(24));
+    if from > to {
+        return Err(CoinFetchError::ValidationError(
+            "`from` must be earlier than `to`".into(),
+        ));
+    }
+    let snapshots = sqlx::query_as!(
+        HistorySnapshot,
+        r#"
+            SELECT
+                recorded_at,
+                current_price,
+                market_cap,
+                market_cap_rank,
+                total_volume,
+                price_change_percentage_24h,
+                last_updated
+            FROM market_data_history
+            WHERE id = $1 AND currency = $2 AND source = $3 AND recorded_at BETWEEN $4 AND $5
+            ORDER BY recorded_at
+            "#,
+        id,
+        currency.as_str(),
+        source,
+        from,
+        to,
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?;
+    if snapshots.is_empty() {
+        return Err(CoinFetchError::NotFoundError(format!(
+            "History for {} not found !",
+            id
+        )));
+    }
+    Ok(HttpResponse::Ok().json(HistoryResponse {
+        id,
+        vs: currency.as_str().to_string(),
+        source,
+        from,
+        to,
+        snapshots,
+    }))
+}
@@ -1,13 +1,23 @@
 use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
 // use anyhow::Context;
 use sqlx::{PgPool, Postgres, Transaction};
 
 use super::{CoinFetchError, StoreTokenError};
-use crate::{domains::Currency, gecko_client::GeckoClient};
+use crate::{
+    domains::{Currency, MarketData, Roi},
+    exchange_rates::conversion_rate,
+    startup::MarketDefaults,
+};
 
 #[derive(serde::Deserialize, Debug)]
 pub struct PathData {
     symbol: String,
+    vs: Option<String>,
+    source: Option<String>,
+    /// Inline the 7 day sparkline in the response.
+    #[serde(default)]
+    sparkline: bool,
 }
 // impl TryFrom<PathData> for Params {
 //     type Error = String;
@@ -19,40 +29,12 @@ pub struct PathData {
 //     }
 // }
 
-#[derive(serde::Deserialize, serde::Serialize)]
-pub struct MarketData {
-    pub id: Option<String>,
-    pub symbol: Option<String>,
-    pub name: Option<String>,
-    pub image: Option<String>,
-    pub current_price: Option<f64>,
-    pub market_cap: Option<f64>,
-    pub market_cap_rank: Option<i32>,
-    pub fully_diluted_valuation: Option<f64>,
-    pub total_volume: Option<f64>,
-    pub high_24h: Option<f64>,
-    pub low_24h: Option<f64>,
-    pub price_change_24h: Option<f64>,
-    pub price_change_percentage_24h: Option<f64>,
-    pub market_cap_change_24h: Option<f64>,
-    pub market_cap_change_percentage_24h: Option<f64>,
-    pub circulating_supply: Option<f64>,
-    pub total_supply: Option<f64>,
-    pub max_supply: Option<f64>,
-    pub ath: Option<f64>,
-    pub ath_change_percentage: Option<f64>,
-    pub ath_date: Option<String>,
-    pub atl: Option<f64>,
-    pub atl_change_percentage: Option<f64>,
-    pub atl_date: Option<String>,
-    // pub roi: Option<f64>,
-    pub last_updated: Option<String>,
-}
-
 #[derive( serde::Serialize)]
 pub struct ResponseData {
     pub id: String,
     pub symbol: String,
+    pub currency: String,
+    pub source: String,
     pub name: Option<String>,
     pub image: Option<String>,
     pub current_price: Option<f64>,
@@ -69,56 +51,213 @@ pub struct ResponseData {
     pub circulating_supply: Option<f64>,
     pub total_supply: Option<f64>,
     pub max_supply: Option<f64>,
+    pub roi: Option<Roi>,
+    pub price_change_percentage_1h_in_currency: Option<f64>,
+    pub price_change_percentage_24h_in_currency: Option<f64>,
+    pub price_change_percentage_7d_in_currency: Option<f64>,
+    pub price_change_percentage_14d_in_currency: Option<f64>,
+    pub price_change_percentage_30d_in_currency: Option<f64>,
+    pub price_change_percentage_200d_in_currency: Option<f64>,
+    pub price_change_percentage_1y_in_currency: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sparkline_7d: Option<Vec<f64>>,
+    pub aggregate: Option<AggregateData>,
+    pub sources: Vec<SourceQuote>,
+    /// Set when the currency is not swept and the amounts were converted from another one.
+    /// Percentages are those of the original currency.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub derived_from: Option<DerivedFrom>,
 }
+
+#[derive(serde::Serialize)]
+pub struct DerivedFrom {
+    pub currency: String,
+    pub rate: f64,
+    pub rate_updated_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+pub struct AggregateData {
+    pub price: f64,
+    pub min_price: f64,
+    pub max_price: f64,
+    pub spread_percentage: f64,
+    pub source_count: i32,
+    pub sources: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SourceQuote {
+    pub source: String,
+    pub current_price: Option<f64>,
+    pub total_volume: Option<f64>,
+    pub last_updated: Option<String>,
+}
+
+impl ResponseData {
+    /// Converts every amount to `currency` at `rate` units per unit of the stored currency.
+    fn convert(mut self, currency: &Currency, rate: f64, rate_updated_at: DateTime<Utc>) -> Self {
+        let convert = |amount: &mut Option<f64>| *amount = amount.map(|a| a * rate);
+        convert(&mut self.current_price);
+        convert(&mut self.market_cap);
+        convert(&mut self.fully_diluted_valuation);
+        convert(&mut self.total_volume);
+        convert(&mut self.high_24h);
+        convert(&mut self.low_24h);
+        convert(&mut self.price_change_24h);
+        convert(&mut self.market_cap_change_24h);
+        if let Some(sparkline) = &mut self.sparkline_7d {
+            sparkline.iter_mut().for_each(|price| *price *= rate);
+        }
+        if let Some(aggregate) = &mut self.aggregate {
+            aggregate.price *= rate;
+            aggregate.min_price *= rate;
+            aggregate.max_price *= rate;
+        }
+        for source in &mut self.sources {
+            convert(&mut source.current_price);
+            convert(&mut source.total_volume);
+        }
+        self.derived_from = Some(DerivedFrom {
+            currency: std::mem::replace(&mut self.currency, currency.as_str().to_string()),
+            rate,
+            rate_updated_at,
+        });
+        self
+    }
+}
+
+/// Serves the stored market data of a coin. Currencies the worker does not sweep
+/// are derived from the USD data with the stored exchange rates.
 pub async fn get_coin_market_details(
     path: web::Query<PathData>,
     pool: web::Data<PgPool>,
+    defaults: web::Data<MarketDefaults>,
 ) -> Result<HttpResponse, CoinFetchError> {
-    let symbol = path.into_inner().symbol;
-    let result = sqlx::query!(r#"SELECT * FROM market_data WHERE symbol = $1"#, symbol,)
+    let PathData { symbol, vs, source, sparkline } = path.into_inner();
+    let currency = match vs {
+        Some(vs) => Currency::try_from(vs).map_err(CoinFetchError::ValidationError)?,
+        None => defaults.currency,
+    };
+    let result = sqlx::query!(
+        r#"
+            SELECT * FROM market_data
+            WHERE symbol = $1 AND currency IN ($2, 'usd') AND source = $3
+            ORDER BY currency = $2 DESC
+            LIMIT 1
+            "#,
+        symbol,
+        currency.as_str(),
+        source.unwrap_or_else(|| defaults.source.clone()),
+    )
         .fetch_one(pool.as_ref())
         .await
-        .map_err(|_| CoinFetchError::NotFoundError(format!("Data for {} not found !",symbol)))?;
-    Ok(HttpResponse::Ok().json(
-        ResponseData {
-            id: result.id,
-            symbol: result.symbol,
-            name: result.name,
-            image: result.image,
-            current_price: result.current_price,
-            market_cap: result.market_cap,
-            market_cap_rank: result.market_cap_rank,
-            fully_diluted_valuation: result.fully_diluted_valuation,
-            total_volume: result.total_volume,
-            high_24h: result.high_24h,
-            low_24h: result.low_24h,
-            price_change_24h: result.price_change_24h,
-            price_change_percentage_24h: result.price_change_percentage_24h,
-            market_cap_change_24h: result.market_cap_change_24h,
-            market_cap_change_percentage_24h: result.market_cap_change_percentage_24h,
-            circulating_supply: result.circulating_supply,
-            total_supply: result.total_supply,
-            max_supply: result.max_supply,
-        }
-    ))
-}
-
-pub async fn coin_market_details(
-    client: &GeckoClient,
-    currency: &Currency,
-    page: u16,
-) -> Result<Vec<Option<MarketData>>, CoinFetchError> {
-    let result = client
-    .get_request(&format!("coins/markets?vs_currency={}&order=market_cap_desc&per_page=250&page={}&sparkline=false", currency.as_str(),page))
-        .await?
-        .json::<Vec<Option<MarketData>>>().await.map_err(|e| CoinFetchError::UnexpectedError(anyhow::anyhow!(e.to_string())))?;
-    Ok(result)
+        .map_err(|_| CoinFetchError::NotFoundError(format!("Data for {} in {} not found !",symbol, currency.as_str())))?;
+    let conversion = if result.currency != currency.as_str() {
+        let conversion = conversion_rate(pool.as_ref(), &Currency::USD, &currency)
+            .await
+            .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?
+            .ok_or_else(|| {
+                CoinFetchError::NotFoundError(format!(
+                    "Data for {} in {} not found !",
+                    symbol,
+                    currency.as_str()
+                ))
+            })?;
+        Some(conversion)
+    } else {
+        None
+    };
+    let aggregate = sqlx::query_as!(
+        AggregateData,
+        r#"
+            SELECT price, min_price, max_price, spread_percentage, source_count, sources, updated_at
+            FROM aggregated_prices
+            WHERE id = $1 AND currency = $2
+            "#,
+        result.id,
+        result.currency,
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?;
+    let sources = sqlx::query_as!(
+        SourceQuote,
+        r#"
+            SELECT source, current_price, total_volume, last_updated
+            FROM market_data
+            WHERE id = $1 AND currency = $2
+            ORDER BY source
+            "#,
+        result.id,
+        result.currency,
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?;
+    let response = ResponseData {
+        id: result.id,
+        symbol: result.symbol,
+        currency: result.currency,
+        source: result.source,
+        name: result.name,
+        image: result.image,
+        current_price: result.current_price,
+        market_cap: result.market_cap,
+        market_cap_rank: result.market_cap_rank,
+        fully_diluted_valuation: result.fully_diluted_valuation,
+        total_volume: result.total_volume,
+        high_24h: result.high_24h,
+        low_24h: result.low_24h,
+        price_change_24h: result.price_change_24h,
+        price_change_percentage_24h: result.price_change_percentage_24h,
+        market_cap_change_24h: result.market_cap_change_24h,
+        market_cap_change_percentage_24h: result.market_cap_change_percentage_24h,
+        circulating_supply: result.circulating_supply,
+        total_supply: result.total_supply,
+        max_supply: result.max_supply,
+        roi: match (result.roi_times, result.roi_currency, result.roi_percentage) {
+            (Some(times), Some(currency), Some(percentage)) => Some(Roi { times, currency, percentage }),
+            _ => None,
+        },
+        price_change_percentage_1h_in_currency: result.price_change_percentage_1h_in_currency,
+        price_change_percentage_24h_in_currency: result.price_change_percentage_24h_in_currency,
+        price_change_percentage_7d_in_currency: result.price_change_percentage_7d_in_currency,
+        price_change_percentage_14d_in_currency: result.price_change_percentage_14d_in_currency,
+        price_change_percentage_30d_in_currency: result.price_change_percentage_30d_in_currency,
+        price_change_percentage_200d_in_currency: result.price_change_percentage_200d_in_currency,
+        price_change_percentage_1y_in_currency: result.price_change_percentage_1y_in_currency,
+        sparkline_7d: result.sparkline_7d.filter(|_| sparkline),
+        aggregate,
+        sources,
+        derived_from: None,
+    };
+    Ok(HttpResponse::Ok().json(match conversion {
+        Some(conversion) => response.convert(&currency, conversion.rate, conversion.updated_at),
+        None => response,
+    }))
 }
 
 pub async fn store_market_data(
     transaction: &mut Transaction<'_, Postgres>,
     data: &MarketData,
+    currency: &Currency,
+    source: &str,
 ) -> Result<(), StoreTokenError> {
+    // Coins the registry has not seen yet get a stub entry, completed by its next refresh.
+    sqlx::query!(
+        r#"
+            INSERT INTO coins (id, symbol, name) VALUES ($1, $2, COALESCE($3, $2))
+            ON CONFLICT (id) DO NOTHING
+            "#,
+        data.id,
+        data.symbol,
+        data.name,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(StoreTokenError)?;
     sqlx::query!(
         r#"
             INSERT INTO market_data (
@@ -146,7 +285,20 @@ pub async fn store_market_data(
                 atl,
                 atl_change_percentage,
                 atl_date,
-                last_updated
+                last_updated,
+                currency,
+                source,
+                sparkline_7d,
+                roi_times,
+                roi_currency,
+                roi_percentage,
+                price_change_percentage_1h_in_currency,
+                price_change_percentage_24h_in_currency,
+                price_change_percentage_7d_in_currency,
+                price_change_percentage_14d_in_currency,
+                price_change_percentage_30d_in_currency,
+                price_change_percentage_200d_in_currency,
+                price_change_percentage_1y_in_currency
             ) VALUES (
                 $1,
                 $2,
@@ -172,9 +324,22 @@ pub async fn store_market_data(
                 $22,
                 $23,
                 $24,
-                $25
+                $25,
+                $26,
+                $27,
+                $28,
+                $29,
+                $30,
+                $31,
+                $32,
+                $33,
+                $34,
+                $35,
+                $36,
+                $37,
+                $38
             )
-            ON CONFLICT (id) DO UPDATE SET
+            ON CONFLICT (id, currency, source) DO UPDATE SET
                 symbol = $2,
                 name = $3,
                 image = $4,
@@ -198,7 +363,19 @@ pub async fn store_market_data(
                 atl = $22,
                 atl_change_percentage = $23,
                 atl_date = $24,
-                last_updated = $25
+                last_updated = $25,
+                sparkline_7d = $28,
+                roi_times = $29,
+                roi_currency = $30,
+                roi_percentage = $31,
+                price_change_percentage_1h_in_currency = $32,
+                price_change_percentage_24h_in_currency = $33,
+                price_change_percentage_7d_in_currency = $34,
+                price_change_percentage_14d_in_currency = $35,
+                price_change_percentage_30d_in_currency = $36,
+                price_change_percentage_200d_in_currency = $37,
+                price_change_percentage_1y_in_currency = $38,
+                updated_at = CURRENT_TIMESTAMP
             "#,
         data.id,
         data.symbol,
@@ -224,10 +401,429 @@ pub async fn store_market_data(
         data.atl,
         data.atl_change_percentage,
         data.atl_date,
-        data.last_updated
+        data.last_updated,
+        currency.as_str(),
+        source,
+        data.sparkline_in_7d.as_ref().map(|s| s.price.as_slice()),
+        data.roi.as_ref().map(|roi| roi.times),
+        data.roi.as_ref().map(|roi| roi.currency.clone()),
+        data.roi.as_ref().map(|roi| roi.percentage),
+        data.price_change_percentage_1h_in_currency,
+        data.price_change_percentage_24h_in_currency,
+        data.price_change_percentage_7d_in_currency,
+        data.price_change_percentage_14d_in_currency,
+        data.price_change_percentage_30d_in_currency,
+        data.price_change_percentage_200d_in_currency,
+        data.price_change_percentage_1y_in_currency,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(StoreTokenError)?;
+    sqlx::query!(
+        r#"
+            INSERT INTO market_data_history (
+                id,
+                currency,
+                current_price,
+                market_cap,
+                market_cap_rank,
+                total_volume,
+                price_change_percentage_24h,
+                last_updated,
+                source
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT DO NOTHING
+            "#,
+        data.id,
+        currency.as_str(),
+        data.current_price,
+        data.market_cap,
+        data.market_cap_rank,
+        data.total_volume,
+        data.price_change_percentage_24h,
+        data.last_updated,
+        source
     )
     .execute(transaction)
     .await
     .map_err(StoreTokenError)?;
     Ok(())
 }
+
+/// Upserts a whole page and its history snapshots in a single statement by
+/// passing every column as an array and expanding them with `UNNEST`.
+/// Fails as a whole, e.g. when the page contains the same coin twice.
+pub async fn store_market_page(
+    transaction: &mut Transaction<'_, Postgres>,
+    page: &[&MarketData],
+    currency: &Currency,
+    source: &str,
+) -> Result<(), StoreTokenError> {
+    sqlx::query!(
+        r#"
+            WITH page AS (
+                SELECT * FROM UNNEST(
+                    $1::text[],
+                    $2::text[],
+                    $3::text[],
+                    $4::text[],
+                    $5::float8[],
+                    $6::float8[],
+                    $7::int4[],
+                    $8::float8[],
+                    $9::float8[],
+                    $10::float8[],
+                    $11::float8[],
+                    $12::float8[],
+                    $13::float8[],
+                    $14::float8[],
+                    $15::float8[],
+                    $16::float8[],
+                    $17::float8[],
+                    $18::float8[],
+                    $19::float8[],
+                    $20::float8[],
+                    $21::text[],
+                    $22::float8[],
+                    $23::float8[],
+                    $24::text[],
+                    $25::text[],
+                    $28::float8[],
+                    $29::text[],
+                    $30::float8[],
+                    $31::float8[],
+                    $32::float8[],
+                    $33::float8[],
+                    $34::float8[],
+                    $35::float8[],
+                    $36::float8[],
+                    $37::float8[]
+                ) AS page (
+                    id,
+                    symbol,
+                    name,
+                    image,
+                    current_price,
+                    market_cap,
+                    market_cap_rank,
+                    fully_diluted_valuation,
+                    total_volume,
+                    high_24h,
+                    low_24h,
+                    price_change_24h,
+                    price_change_percentage_24h,
+                    market_cap_change_24h,
+                    market_cap_change_percentage_24h,
+                    circulating_supply,
+                    total_supply,
+                    max_supply,
+                    ath,
+                    ath_change_percentage,
+                    ath_date,
+                    atl,
+                    atl_change_percentage,
+                    atl_date,
+                    last_updated,
+                    roi_times,
+                    roi_currency,
+                    roi_percentage,
+                    price_change_percentage_1h_in_currency,
+                    price_change_percentage_24h_in_currency,
+                    price_change_percentage_7d_in_currency,
+                    price_change_percentage_14d_in_currency,
+                    price_change_percentage_30d_in_currency,
+                    price_change_percentage_200d_in_currency,
+                    price_change_percentage_1y_in_currency
+                )
+            ),
+            registered AS (
+                INSERT INTO coins (id, symbol, name)
+                SELECT id, symbol, COALESCE(name, symbol) FROM page
+                ON CONFLICT (id) DO NOTHING
+            ),
+            upserted AS (
+                INSERT INTO market_data (
+                    id,
+                    symbol,
+                    name,
+                    image,
+                    current_price,
+                    market_cap,
+                    market_cap_rank,
+                    fully_diluted_valuation,
+                    total_volume,
+                    high_24h,
+                    low_24h,
+                    price_change_24h,
+                    price_change_percentage_24h,
+                    market_cap_change_24h,
+                    market_cap_change_percentage_24h,
+                    circulating_supply,
+                    total_supply,
+                    max_supply,
+                    ath,
+                    ath_change_percentage,
+                    ath_date,
+                    atl,
+                    atl_change_percentage,
+                    atl_date,
+                    last_updated,
+                    roi_times,
+                    roi_currency,
+                    roi_percentage,
+                    price_change_percentage_1h_in_currency,
+                    price_change_percentage_24h_in_currency,
+                    price_change_percentage_7d_in_currency,
+                    price_change_percentage_14d_in_currency,
+                    price_change_percentage_30d_in_currency,
+                    price_change_percentage_200d_in_currency,
+                    price_change_percentage_1y_in_currency,
+                    currency,
+                    source
+                )
+                SELECT *, $26, $27 FROM page
+                ON CONFLICT (id, currency, source) DO UPDATE SET
+                    symbol = EXCLUDED.symbol,
+                    name = EXCLUDED.name,
+                    image = EXCLUDED.image,
+                    current_price = EXCLUDED.current_price,
+                    market_cap = EXCLUDED.market_cap,
+                    market_cap_rank = EXCLUDED.market_cap_rank,
+                    fully_diluted_valuation = EXCLUDED.fully_diluted_valuation,
+                    total_volume = EXCLUDED.total_volume,
+                    high_24h = EXCLUDED.high_24h,
+                    low_24h = EXCLUDED.low_24h,
+                    price_change_24h = EXCLUDED.price_change_24h,
+                    price_change_percentage_24h = EXCLUDED.price_change_percentage_24h,
+                    market_cap_change_24h = EXCLUDED.market_cap_change_24h,
+                    market_cap_change_percentage_24h = EXCLUDED.market_cap_change_percentage_24h,
+                    circulating_supply = EXCLUDED.circulating_supply,
+                    total_supply = EXCLUDED.total_supply,
+                    max_supply = EXCLUDED.max_supply,
+                    ath = EXCLUDED.ath,
+                    ath_change_percentage = EXCLUDED.ath_change_percentage,
+                    ath_date = EXCLUDED.ath_date,
+                    atl = EXCLUDED.atl,
+                    atl_change_percentage = EXCLUDED.atl_change_percentage,
+                    atl_date = EXCLUDED.atl_date,
+                    last_updated = EXCLUDED.last_updated,
+                    roi_times = EXCLUDED.roi_times,
+                    roi_currency = EXCLUDED.roi_currency,
+                    roi_percentage = EXCLUDED.roi_percentage,
+                    price_change_percentage_1h_in_currency = EXCLUDED.price_change_percentage_1h_in_currency,
+                    price_change_percentage_24h_in_currency = EXCLUDED.price_change_percentage_24h_in_currency,
+                    price_change_percentage_7d_in_currency = EXCLUDED.price_change_percentage_7d_in_currency,
+                    price_change_percentage_14d_in_currency = EXCLUDED.price_change_percentage_14d_in_currency,
+                    price_change_percentage_30d_in_currency = EXCLUDED.price_change_percentage_30d_in_currency,
+                    price_change_percentage_200d_in_currency = EXCLUDED.price_change_percentage_200d_in_currency,
+                    price_change_percentage_1y_in_currency = EXCLUDED.price_change_percentage_1y_in_currency,
+                    updated_at = CURRENT_TIMESTAMP
+            )
+            INSERT INTO market_data_history (
+                id,
+                currency,
+                current_price,
+                market_cap,
+                market_cap_rank,
+                total_volume,
+                price_change_percentage_24h,
+                last_updated,
+                source
+            )
+            SELECT
+                id,
+                $26,
+                current_price,
+                market_cap,
+                market_cap_rank,
+                total_volume,
+                price_change_percentage_24h,
+                last_updated,
+                $27
+            FROM page
+            ON CONFLICT DO NOTHING
+            "#,
+        &page.iter().map(|d| d.id.clone()).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.symbol.clone()).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.name.clone()).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.image.clone()).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.current_price).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.market_cap).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.market_cap_rank).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.fully_diluted_valuation).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.total_volume).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.high_24h).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.low_24h).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.price_change_24h).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.price_change_percentage_24h).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.market_cap_change_24h).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.market_cap_change_percentage_24h).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.circulating_supply).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.total_supply).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.max_supply).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.ath).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.ath_change_percentage).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.ath_date.clone()).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.atl).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.atl_change_percentage).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.atl_date.clone()).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.last_updated.clone()).collect::<Vec<_>>() as _,
+        currency.as_str(),
+        source,
+        &page.iter().map(|d| d.roi.as_ref().map(|roi| roi.times)).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.roi.as_ref().map(|roi| roi.currency.clone())).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.roi.as_ref().map(|roi| roi.percentage)).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.price_change_percentage_1h_in_currency).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.price_change_percentage_24h_in_currency).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.price_change_percentage_7d_in_currency).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.price_change_percentage_14d_in_currency).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.price_change_percentage_30d_in_currency).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.price_change_percentage_200d_in_currency).collect::<Vec<_>>() as _,
+        &page.iter().map(|d| d.price_change_percentage_1y_in_currency).collect::<Vec<_>>() as _,
+    )
+    .execute(&mut *transaction)
+    .await
+    .map_err(StoreTokenError)?;
+    // Sparklines are arrays themselves, which UNNEST would flatten, so they
+    // travel as JSON arrays in a second statement.
+    let sparklines = page
+        .iter()
+        .map(|d| {
+            d.sparkline_in_7d
+                .as_ref()
+                .map(|s| serde_json::json!(s.price))
+                .unwrap_or(serde_json::Value::Null)
+        })
+        .collect::<Vec<_>>();
+    sqlx::query!(
+        r#"
+            UPDATE market_data SET
+                sparkline_7d = CASE
+                    WHEN jsonb_typeof(page.sparkline) = 'array'
+                    THEN ARRAY(SELECT jsonb_array_elements_text(page.sparkline)::float8)
+                END
+            FROM UNNEST($1::text[], $2::jsonb[]) AS page (id, sparkline)
+            WHERE market_data.id = page.id AND market_data.currency = $3 AND market_data.source = $4
+            "#,
+        &page.iter().map(|d| d.id.clone()).collect::<Vec<_>>() as _,
+        &sparklines,
+        currency.as_str(),
+        source,
+    )
+    .execute(transaction)
+    .await
+    .map_err(StoreTokenError)?;
+    Ok(())
+}
+
+/// Keeps a row that could not be stored, with the reason, for later inspection.
+pub async fn store_rejected_market_data(
+    transaction: &mut Transaction<'_, Postgres>,
+    data: &MarketData,
+    currency: &Currency,
+    source: &str,
+    error: &str,
+) -> Result<(), StoreTokenError> {
+    let payload = serde_json::to_value(data).unwrap_or_default();
+    sqlx::query!(
+        r#"
+            INSERT INTO rejected_market_data (coin_id, currency, source, payload, error)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        data.id,
+        currency.as_str(),
+        source,
+        payload,
+        error,
+    )
+    .execute(transaction)
+    .await
+    .map_err(StoreTokenError)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usd_data() -> ResponseData {
+        let now = Utc::now();
+        ResponseData {
+            id: "bitcoin".to_string(),
+            symbol: "btc".to_string(),
+            currency: "usd".to_string(),
+            source: "coingecko".to_string(),
+            name: Some("Bitcoin".to_string()),
+            image: None,
+            current_price: Some(100.0),
+            market_cap: Some(1000.0),
+            market_cap_rank: Some(1),
+            fully_diluted_valuation: None,
+            total_volume: Some(50.0),
+            high_24h: Some(110.0),
+            low_24h: Some(90.0),
+            price_change_24h: Some(-10.0),
+            price_change_percentage_24h: Some(-9.0),
+            market_cap_change_24h: Some(20.0),
+            market_cap_change_percentage_24h: Some(2.0),
+            circulating_supply: Some(10.0),
+            total_supply: None,
+            max_supply: None,
+            roi: None,
+            price_change_percentage_1h_in_currency: Some(1.5),
+            price_change_percentage_24h_in_currency: None,
+            price_change_percentage_7d_in_currency: None,
+            price_change_percentage_14d_in_currency: None,
+            price_change_percentage_30d_in_currency: None,
+            price_change_percentage_200d_in_currency: None,
+            price_change_percentage_1y_in_currency: None,
+            sparkline_7d: Some(vec![80.0, 100.0]),
+            aggregate: Some(AggregateData {
+                price: 100.0,
+                min_price: 98.0,
+                max_price: 102.0,
+                spread_percentage: 4.0,
+                source_count: 2,
+                sources: vec!["coingecko".to_string(), "binance".to_string()],
+                updated_at: now,
+            }),
+            sources: vec![SourceQuote {
+                source: "binance".to_string(),
+                current_price: Some(102.0),
+                total_volume: None,
+                last_updated: None,
+            }],
+            derived_from: None,
+        }
+    }
+
+    #[test]
+    fn convert_scales_every_amount_and_keeps_percentages() {
+        let rate_updated_at = Utc::now();
+
+        let data = usd_data().convert(&Currency::EUR, 0.5, rate_updated_at);
+
+        assert_eq!(data.currency, "eur");
+        assert_eq!(data.current_price, Some(50.0));
+        assert_eq!(data.market_cap, Some(500.0));
+        assert_eq!(data.fully_diluted_valuation, None);
+        assert_eq!(data.total_volume, Some(25.0));
+        assert_eq!((data.high_24h, data.low_24h), (Some(55.0), Some(45.0)));
+        assert_eq!(data.price_change_24h, Some(-5.0));
+        assert_eq!(data.market_cap_change_24h, Some(10.0));
+        assert_eq!(data.sparkline_7d, Some(vec![40.0, 50.0]));
+        let aggregate = data.aggregate.as_ref().unwrap();
+        assert_eq!((aggregate.price, aggregate.min_price, aggregate.max_price), (50.0, 49.0, 51.0));
+        assert_eq!(aggregate.spread_percentage, 4.0);
+        assert_eq!(data.sources[0].current_price, Some(51.0));
+        // Neither percentages nor quantities of coins depend on the currency.
+        assert_eq!(data.price_change_percentage_24h, Some(-9.0));
+        assert_eq!(data.market_cap_change_percentage_24h, Some(2.0));
+        assert_eq!(data.price_change_percentage_1h_in_currency, Some(1.5));
+        assert_eq!(data.circulating_supply, Some(10.0));
+        let derived_from = data.derived_from.unwrap();
+        assert_eq!(derived_from.currency, "usd");
+        assert_eq!(derived_from.rate, 0.5);
+        assert_eq!(derived_from.rate_updated_at, rate_updated_at);
+    }
+}
@@ -0,0 +1,250 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use super::CoinFetchError;
+use crate::{
+    domains::{CandleInterval, Currency},
+    startup::MarketDefaults,
+};
+
+/// Candles returned when `from` is left out.
+const DEFAULT_CANDLES: i32 = 100;
+/// Most candle periods a single request may span.
+const MAX_CANDLES: i64 = 5000;
+
+#[derive(serde::Deserialize, Debug)]
+pub struct OhlcPath {
+    id: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct OhlcQuery {
+    interval: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    vs: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct OhlcCandle {
+    pub open_time: DateTime<Utc>,
+    pub close_time: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+/// A stretch of the requested range without any stored candle.
+#[derive(serde::Serialize)]
+pub struct CandleGap {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub missing_candles: i64,
+}
+
+#[derive(serde::Serialize)]
+pub struct OhlcResponse {
+    pub id: String,
+    pub vs: String,
+    pub interval: CandleInterval,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub candles: Vec<OhlcCandle>,
+    /// Empty when the candles cover the whole range.
+    pub gaps: Vec<CandleGap>,
+}
+
+/// Returns the stored candles of a coin closing between `from` and `to`, along with
+/// the gaps where candles are missing. Defaults to the last 100 candles in the default currency.
+pub async fn get_coin_ohlc(
+    path: web::Path<OhlcPath>,
+    query: web::Query<OhlcQuery>,
+    pool: web::Data<PgPool>,
+    defaults: web::Data<MarketDefaults>,
+) -> Result<HttpResponse, CoinFetchError> {
+    let id = path.into_inner().id;
+    let query = query.into_inner();
+    let interval = query
+        .interval
+        .ok_or_else(|| CoinFetchError::ValidationError("`interval` is required".into()))
+        .and_then(|i| CandleInterval::try_from(i).map_err(CoinFetchError::ValidationError))?;
+    let currency = match query.vs {
+        Some(vs) => Currency::try_from(vs).map_err(CoinFetchError::ValidationError)?,
+        None => defaults.currency,
+    };
+    let period = interval.duration();
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - period * DEFAULT_CANDLES);
+    if from > to {
+        return Err(CoinFetchError::ValidationError(
+            "`from` must be earlier than `to`".into(),
+        ));
+    }
+    if (to - from).num_seconds() / period.num_seconds() > MAX_CANDLES {
+        return Err(CoinFetchError::ValidationError(format!(
+            "The range spans more than {} {} candles",
+            MAX_CANDLES,
+            interval.as_str()
+        )));
+    }
+    let tracked = sqlx::query!(
+        r#"
+            SELECT EXISTS (
+                SELECT 1 FROM ohlc_candles WHERE coin_id = $1 AND currency = $2 AND interval = $3
+            ) AS "tracked!"
+            "#,
+        id,
+        currency.as_str(),
+        interval.as_str(),
+    )
+    .fetch_one(pool.as_ref())
+    .await
+    .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?
+    .tracked;
+    if !tracked {
+        return Err(CoinFetchError::NotFoundError(format!(
+            "{} candles for {} not found !",
+            interval.as_str(),
+            id
+        )));
+    }
+    let rows = sqlx::query!(
+        r#"
+            SELECT close_time, open, high, low, close
+            FROM ohlc_candles
+            WHERE coin_id = $1 AND currency = $2 AND interval = $3 AND close_time BETWEEN $4 AND $5
+            ORDER BY close_time
+            "#,
+        id,
+        currency.as_str(),
+        interval.as_str(),
+        from,
+        to,
+    )
+    .fetch_all(pool.as_ref())
+    .await
+    .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?;
+    let candles: Vec<OhlcCandle> = rows
+        .into_iter()
+        .map(|row| OhlcCandle {
+            open_time: row.close_time - period,
+            close_time: row.close_time,
+            open: row.open,
+            high: row.high,
+            low: row.low,
+            close: row.close,
+        })
+        .collect();
+    let gaps = find_gaps(&candles, from, to, period);
+    Ok(HttpResponse::Ok().json(OhlcResponse {
+        id,
+        vs: currency.as_str().to_string(),
+        interval,
+        from,
+        to,
+        candles,
+        gaps,
+    }))
+}
+
+/// Every stretch of at least one whole period between `from`, the candles and `to`.
+fn find_gaps(
+    candles: &[OhlcCandle],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    period: chrono::Duration,
+) -> Vec<CandleGap> {
+    let mut gaps = vec![];
+    let mut covered_until = from;
+    let bounds = candles
+        .iter()
+        .map(|c| (c.open_time, c.close_time))
+        .chain(std::iter::once((to, to)));
+    for (start, end) in bounds {
+        let missing_candles = (start - covered_until).num_seconds() / period.num_seconds();
+        if missing_candles > 0 {
+            gaps.push(CandleGap { from: covered_until, to: start, missing_candles });
+        }
+        covered_until = covered_until.max(end);
+    }
+    gaps
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const PERIOD_MINUTES: i64 = 30;
+
+    /// `minutes` after a fixed, period-aligned origin.
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2023, 4, 1, 0, 0, 0).unwrap() + chrono::Duration::minutes(minutes)
+    }
+
+    fn candle(open_minutes: i64) -> OhlcCandle {
+        OhlcCandle {
+            open_time: at(open_minutes),
+            close_time: at(open_minutes + PERIOD_MINUTES),
+            open: 1.0,
+            high: 1.0,
+            low: 1.0,
+            close: 1.0,
+        }
+    }
+
+    fn gaps(candles: &[OhlcCandle], from: i64, to: i64) -> Vec<(i64, i64, i64)> {
+        find_gaps(candles, at(from), at(to), chrono::Duration::minutes(PERIOD_MINUTES))
+            .into_iter()
+            .map(|gap| {
+                (
+                    (gap.from - at(0)).num_minutes(),
+                    (gap.to - at(0)).num_minutes(),
+                    gap.missing_candles,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn contiguous_candles_leave_no_gap() {
+        assert!(gaps(&[candle(0), candle(30), candle(60)], 0, 90).is_empty());
+    }
+
+    #[test]
+    fn empty_range_is_a_single_gap() {
+        assert_eq!(gaps(&[], 0, 120), vec![(0, 120, 4)]);
+    }
+
+    #[test]
+    fn gap_before_the_first_candle() {
+        assert_eq!(gaps(&[candle(60), candle(90)], 0, 120), vec![(0, 60, 2)]);
+    }
+
+    #[test]
+    fn gap_between_candles() {
+        assert_eq!(gaps(&[candle(0), candle(90)], 0, 120), vec![(30, 90, 2)]);
+    }
+
+    #[test]
+    fn gap_after_the_last_candle() {
+        assert_eq!(gaps(&[candle(0), candle(30)], 0, 150), vec![(60, 150, 3)]);
+    }
+
+    #[test]
+    fn overlapping_candles_count_once() {
+        // The second candle is covered by the first up to minute 30.
+        assert!(gaps(&[candle(0), candle(15), candle(45)], 0, 75).is_empty());
+        assert_eq!(gaps(&[candle(0), candle(15), candle(105)], 0, 135), vec![(45, 105, 2)]);
+    }
+
+    #[test]
+    fn stretches_shorter_than_a_period_are_not_gaps() {
+        // Unaligned candles 15 minutes apart, then a stretch of a period and a half.
+        assert!(gaps(&[candle(0), candle(45)], 0, 75).is_empty());
+        assert_eq!(gaps(&[candle(0), candle(75)], 0, 105), vec![(30, 75, 1)]);
+    }
+}
@@ -0,0 +1,67 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use super::CoinFetchError;
+use crate::{domains::Currency, startup::MarketDefaults};
+
+#[derive(serde::Deserialize, Debug)]
+pub struct SparklinePath {
+    id: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct SparklineQuery {
+    vs: Option<String>,
+    source: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SparklineResponse {
+    pub id: String,
+    pub vs: String,
+    pub source: String,
+    /// The last price of the sparkline is as of this time.
+    pub last_updated: Option<String>,
+    pub updated_at: DateTime<Utc>,
+    /// Hourly prices over the last 7 days, oldest first.
+    pub prices: Vec<f64>,
+}
+
+/// Returns the stored 7 day sparkline of a coin.
+pub async fn get_coin_sparkline(
+    path: web::Path<SparklinePath>,
+    query: web::Query<SparklineQuery>,
+    pool: web::Data<PgPool>,
+    defaults: web::Data<MarketDefaults>,
+) -> Result<HttpResponse, CoinFetchError> {
+    let id = path.into_inner().id;
+    let query = query.into_inner();
+    let currency = match query.vs {
+        Some(vs) => Currency::try_from(vs).map_err(CoinFetchError::ValidationError)?,
+        None => defaults.currency,
+    };
+    let source = query.source.unwrap_or_else(|| defaults.source.clone());
+    let result = sqlx::query!(
+        r#"
+            SELECT last_updated, updated_at, sparkline_7d AS "sparkline_7d!"
+            FROM market_data
+            WHERE id = $1 AND currency = $2 AND source = $3 AND sparkline_7d IS NOT NULL
+            "#,
+        id,
+        currency.as_str(),
+        source,
+    )
+    .fetch_optional(pool.as_ref())
+    .await
+    .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?
+    .ok_or_else(|| CoinFetchError::NotFoundError(format!("Sparkline for {} not found !", id)))?;
+    Ok(HttpResponse::Ok().json(SparklineResponse {
+        id,
+        vs: currency.as_str().to_string(),
+        source,
+        last_updated: result.last_updated,
+        updated_at: result.updated_at,
+        prices: result.sparkline_7d,
+    }))
+}
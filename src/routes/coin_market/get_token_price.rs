@@ -0,0 +1,144 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use super::CoinFetchError;
+use crate::{
+    coin_registry::{find_contract, platform_exists},
+    domains::{ContractAddress, Currency},
+    live_token_prices::LiveTokenPrices,
+    price_source::{PriceSource, PriceSourceError},
+    startup::MarketDefaults,
+};
+
+#[derive(serde::Deserialize, Debug)]
+pub struct TokenPath {
+    chain: String,
+    contract_address: String,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct TokenPriceQuery {
+    vs: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct TokenPriceResponse {
+    pub chain: String,
+    pub contract_address: String,
+    /// The coin the registry maps the contract to, `None` for unregistered tokens.
+    pub id: Option<String>,
+    pub symbol: Option<String>,
+    pub name: Option<String>,
+    pub vs: String,
+    pub source: String,
+    pub current_price: Option<f64>,
+    pub market_cap: Option<f64>,
+    pub total_volume: Option<f64>,
+    pub price_change_percentage_24h: Option<f64>,
+    pub last_updated: Option<String>,
+    /// `false` when the price was fetched live from `simple/token_price`.
+    pub stored: bool,
+}
+
+/// Returns the price of a token by its contract address on `chain`, a CoinGecko platform id.
+/// Stored market data of the registered coin is served first; tokens the registry
+/// or the worker have not seen are priced live by the price source, within the
+/// budget and through the cache of `live_prices`.
+pub async fn get_token_price(
+    path: web::Path<TokenPath>,
+    query: web::Query<TokenPriceQuery>,
+    pool: web::Data<PgPool>,
+    price_source: web::Data<dyn PriceSource>,
+    live_prices: web::Data<LiveTokenPrices>,
+    defaults: web::Data<MarketDefaults>,
+) -> Result<HttpResponse, CoinFetchError> {
+    let TokenPath { chain, contract_address } = path.into_inner();
+    let chain = chain.to_lowercase();
+    let currency = match query.into_inner().vs {
+        Some(vs) => Currency::try_from(vs).map_err(CoinFetchError::ValidationError)?,
+        None => defaults.currency,
+    };
+    let address = ContractAddress::parse(&contract_address).map_err(CoinFetchError::ValidationError)?;
+    // Chains the registry does not know are never looked up upstream.
+    let known_chain = platform_exists(pool.as_ref(), &chain)
+        .await
+        .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?;
+    if !known_chain {
+        return Err(CoinFetchError::ValidationError(format!("Unknown chain {}", chain)));
+    }
+    let contract = find_contract(pool.as_ref(), &chain, &address)
+        .await
+        .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?;
+    if let Some(contract) = &contract {
+        let stored = sqlx::query!(
+            r#"
+                SELECT source, current_price, market_cap, total_volume, price_change_percentage_24h, last_updated
+                FROM market_data
+                WHERE id = $1 AND currency = $2 AND source = $3
+                "#,
+            contract.coin_id,
+            currency.as_str(),
+            defaults.source,
+        )
+        .fetch_optional(pool.as_ref())
+        .await
+        .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?;
+        if let Some(stored) = stored {
+            return Ok(HttpResponse::Ok().json(TokenPriceResponse {
+                chain,
+                contract_address: contract.contract_address.clone(),
+                id: Some(contract.coin_id.clone()),
+                symbol: Some(contract.symbol.clone()),
+                name: Some(contract.name.clone()),
+                vs: currency.as_str().to_string(),
+                source: stored.source,
+                current_price: stored.current_price,
+                market_cap: stored.market_cap,
+                total_volume: stored.total_volume,
+                price_change_percentage_24h: stored.price_change_percentage_24h,
+                last_updated: stored.last_updated,
+                stored: true,
+            }));
+        }
+    }
+    let key = (chain.clone(), address.as_str().to_string(), currency);
+    let quote = match live_prices.cached(&key) {
+        Some(quote) => quote,
+        None => {
+            if !live_prices.try_acquire() {
+                return Err(CoinFetchError::Unavailable(
+                    "Too many live token price lookups, try again later".into(),
+                ));
+            }
+            let quote = match price_source.fetch_token_quote(&chain, &address, &currency).await {
+                Ok(quote) => quote,
+                // CoinGecko rejects a token it cannot price with a client error.
+                Err(PriceSourceError::GeckoError(e)) if e.is_client_error() => None,
+                Err(e) => return Err(e.into()),
+            };
+            live_prices.store(key, quote.clone());
+            quote
+        }
+    };
+    let quote = quote.ok_or_else(|| {
+        CoinFetchError::NotFoundError(format!(
+            "Token {} on {} not found !",
+            contract_address, chain
+        ))
+    })?;
+    Ok(HttpResponse::Ok().json(TokenPriceResponse {
+        chain,
+        contract_address: quote.contract_address,
+        id: contract.as_ref().map(|c| c.coin_id.clone()),
+        symbol: contract.as_ref().map(|c| c.symbol.clone()),
+        name: contract.map(|c| c.name),
+        vs: currency.as_str().to_string(),
+        source: quote.source,
+        current_price: Some(quote.price),
+        market_cap: quote.market_cap,
+        total_volume: quote.volume_24h,
+        price_change_percentage_24h: quote.change_percentage_24h,
+        last_updated: Some(quote.last_updated.to_rfc3339()),
+        stored: false,
+    }))
+}
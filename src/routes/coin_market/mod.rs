@@ -1,4 +1,14 @@
 mod coin_fetch_error;
+mod convert_amount;
 mod get_coin_market_details;
+mod get_coin_history;
+mod get_coin_ohlc;
+mod get_coin_sparkline;
+mod get_token_price;
 pub use coin_fetch_error::{CoinFetchError,StoreTokenError};
-pub use get_coin_market_details::{get_coin_market_details,coin_market_details,store_market_data};
\ No newline at end of file
+pub use convert_amount::convert_amount;
+pub use get_coin_market_details::{get_coin_market_details,store_market_data,store_market_page,store_rejected_market_data};
+pub use get_coin_history::get_coin_history;
+pub use get_coin_ohlc::get_coin_ohlc;
+pub use get_coin_sparkline::get_coin_sparkline;
+pub use get_token_price::get_token_price;
\ No newline at end of file
@@ -1,4 +1,6 @@
 mod health_check;
 mod coin_market;
+mod worker;
 pub use health_check::*;
-pub use coin_market::*;
\ No newline at end of file
+pub use coin_market::*;
+pub use worker::*;
\ No newline at end of file
@@ -0,0 +1,16 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+
+use crate::{leader_election::current_leader, routes::CoinFetchError, startup::WorkerLockId};
+
+/// Returns the instance running the ingestion loop.
+pub async fn get_worker_leader(
+    pool: web::Data<PgPool>,
+    lock_id: web::Data<WorkerLockId>,
+) -> Result<HttpResponse, CoinFetchError> {
+    let leader = current_leader(pool.as_ref(), lock_id.0)
+        .await
+        .map_err(|e| CoinFetchError::UnexpectedError(e.into()))?
+        .ok_or_else(|| CoinFetchError::NotFoundError("No worker leader has been elected yet".into()))?;
+    Ok(HttpResponse::Ok().json(leader))
+}
@@ -0,0 +1,90 @@
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    ingestion_runs::{provider_health, ProviderHealth},
+    leader_election::{current_leader, Leader},
+    market_sweep::{active_sweeps, last_completed_sweep, last_finished_sweeps, Sweep},
+    routes::CoinFetchError,
+    startup::WorkerLockId,
+    worker_schedule::{scheduled_runs, TIER_PREFIX},
+};
+
+#[derive(serde::Serialize)]
+pub struct TierStatus {
+    pub tier: String,
+    pub next_due_at: Option<DateTime<Utc>>,
+    /// The sweep in progress, with the next page to fetch.
+    pub cursor: Option<Sweep>,
+    pub last_sweep: Option<Sweep>,
+}
+
+#[derive(serde::Serialize)]
+pub struct WorkerStatus {
+    pub leader: Option<Leader>,
+    /// The last sweep that reached the last page of its tier.
+    pub last_successful_sweep: Option<Sweep>,
+    /// Tiers that are due or half way through a sweep.
+    pub queue_depth: usize,
+    pub tiers: Vec<TierStatus>,
+    /// Providers that ran within the last `HEALTH_WINDOW_HOURS`.
+    pub providers: Vec<ProviderHealth>,
+}
+
+/// Runs older than this are left out of the provider health.
+const HEALTH_WINDOW_HOURS: i64 = 24;
+
+/// Summarises whether prices are flowing: leader, sweeps per tier and failures per provider.
+pub async fn get_worker_status(
+    pool: web::Data<PgPool>,
+    lock_id: web::Data<WorkerLockId>,
+) -> Result<HttpResponse, CoinFetchError> {
+    let pool = pool.as_ref();
+    let unexpected = |e: sqlx::Error| CoinFetchError::UnexpectedError(e.into());
+    let leader = current_leader(pool, lock_id.0).await.map_err(unexpected)?;
+    let schedule = scheduled_runs(pool, TIER_PREFIX).await.map_err(unexpected)?;
+    let mut active = active_sweeps(pool).await.map_err(unexpected)?;
+    let mut finished = last_finished_sweeps(pool).await.map_err(unexpected)?;
+    let last_successful_sweep = last_completed_sweep(pool).await.map_err(unexpected)?;
+    let since = Utc::now() - chrono::Duration::hours(HEALTH_WINDOW_HOURS);
+    let providers = provider_health(pool, since).await.map_err(unexpected)?;
+
+    let mut names: Vec<String> = schedule
+        .iter()
+        .map(|(name, _)| name.trim_start_matches(TIER_PREFIX).to_string())
+        .chain(active.iter().map(|sweep| sweep.tier.clone()))
+        .collect();
+    names.sort();
+    names.dedup();
+    let now = Utc::now();
+    let tiers: Vec<TierStatus> = names
+        .into_iter()
+        .map(|tier| {
+            let next_due_at = schedule
+                .iter()
+                .find(|(name, _)| name.trim_start_matches(TIER_PREFIX) == tier)
+                .map(|(_, due)| *due);
+            let cursor = active
+                .iter()
+                .position(|sweep| sweep.tier == tier)
+                .map(|i| active.swap_remove(i));
+            let last_sweep = finished
+                .iter()
+                .position(|sweep| sweep.tier == tier)
+                .map(|i| finished.swap_remove(i));
+            TierStatus { tier, next_due_at, cursor, last_sweep }
+        })
+        .collect();
+    let queue_depth = tiers
+        .iter()
+        .filter(|t| t.cursor.is_some() || t.next_due_at.is_some_and(|due| due <= now))
+        .count();
+    Ok(HttpResponse::Ok().json(WorkerStatus {
+        leader,
+        last_successful_sweep,
+        queue_depth,
+        tiers,
+        providers,
+    }))
+}
@@ -0,0 +1,6 @@
+mod get_worker_leader;
+mod get_worker_status;
+mod worker_health_check;
+pub use get_worker_leader::get_worker_leader;
+pub use get_worker_status::get_worker_status;
+pub use worker_health_check::worker_health_check;
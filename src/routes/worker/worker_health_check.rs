@@ -0,0 +1,32 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpResponse};
+use chrono::{DateTime, Utc};
+
+use crate::market_data_worker::WorkerState;
+
+#[derive(serde::Serialize)]
+pub struct WorkerHealth {
+    pub instance_id: String,
+    pub leader: bool,
+    /// The loop has made no progress for longer than the stall timeout.
+    pub stalled: bool,
+    pub last_page_at: Option<DateTime<Utc>>,
+}
+
+/// 503 when this instance leads an ingestion loop that has stopped making progress,
+/// 200 otherwise. A follower is a healthy standby, ready to take over the lock, and
+/// reports `leader: false`; the current leader is served by `/worker/leader`.
+pub async fn worker_health_check(state: web::Data<Arc<WorkerState>>) -> HttpResponse {
+    let health = WorkerHealth {
+        instance_id: state.instance_id.clone(),
+        leader: state.is_leader(),
+        stalled: state.is_stalled(),
+        last_page_at: state.last_page_at(),
+    };
+    if health.stalled {
+        HttpResponse::ServiceUnavailable().json(health)
+    } else {
+        HttpResponse::Ok().json(health)
+    }
+}
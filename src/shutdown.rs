@@ -0,0 +1,38 @@
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::watch,
+};
+
+/// Tells long running tasks that the process is shutting down.
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+/// Sending `true` on the returned sender requests the shutdown.
+pub fn shutdown_channel() -> (watch::Sender<bool>, Shutdown) {
+    let (sender, receiver) = watch::channel(false);
+    (sender, Shutdown(receiver))
+}
+
+impl Shutdown {
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once the shutdown is requested, or once nobody can request it anymore.
+    pub async fn requested(&mut self) {
+        while !self.is_requested() {
+            if self.0.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Resolves on SIGTERM or Ctrl-C.
+pub async fn wait_for_signal() {
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to listen for SIGTERM");
+    tokio::select! {
+        _ = tokio::signal::ctrl_c() => {},
+        _ = terminate.recv() => {},
+    }
+}
@@ -1,30 +1,82 @@
-use actix_web::{dev::Server, web, App, HttpServer};
+use actix_web::{dev::{Server, ServerHandle}, web, App, HttpServer};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::net::TcpListener;
+use std::{net::TcpListener, sync::Arc};
 
 use crate::{
     configuration::{Settings, DatabaseSetting},
-    gecko_client::GeckoClient, routes::{health_check, get_coin_market_details},
+    domains::Currency,
+    gecko_client::GeckoClient,
+    live_token_prices::LiveTokenPrices,
+    market_data_worker::WorkerState,
+    price_source::PriceSource, routes::{health_check, convert_amount, get_coin_market_details, get_coin_history, get_coin_ohlc, get_coin_sparkline, get_token_price, get_worker_leader, get_worker_status, worker_health_check},
 };
 pub struct Application {
     port: u16,
     server: Server,
+    db_pool: PgPool,
+}
+pub struct ApplicationBaseUrl(pub String);
+
+/// Advisory lock the worker instances compete for.
+pub struct WorkerLockId(pub i64);
+
+/// What the `/worker` routes report on: the lock every worker instance competes
+/// for, and the state of the worker running in this process, if any.
+pub struct WorkerRoutes {
+    pub lock_id: i64,
+    pub state: Option<Arc<WorkerState>>,
+}
+
+/// The price source the API asks for what the worker has not stored, and the
+/// cache and budget of its live token lookups.
+pub struct LivePrices {
+    pub source: Arc<dyn PriceSource>,
+    pub tokens: LiveTokenPrices,
+}
+
+/// Currency and source served when a request leaves them out.
+pub struct MarketDefaults {
+    pub currency: Currency,
+    pub source: String,
 }
-pub struct ApplicationBaseUrl(String);
 
 pub fn run(
     listner: TcpListener,
     db_pool: PgPool,
-    gecko_client: GeckoClient,
+    live_prices: LivePrices,
     base_url: String,
+    defaults: MarketDefaults,
+    worker: WorkerRoutes,
+    shutdown_timeout: std::time::Duration,
 ) -> Result<Server, std::io::Error> {
     let db_pool = web::Data::new(db_pool);
-    let gecko_client = web::Data::new(gecko_client);
+    let price_source: web::Data<dyn PriceSource> = web::Data::from(live_prices.source);
+    let live_token_prices = web::Data::new(live_prices.tokens);
     let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let defaults = web::Data::new(defaults);
+    let worker_lock_id = web::Data::new(WorkerLockId(worker.lock_id));
+    let worker_state = worker.state.map(web::Data::new);
     let server = HttpServer::new(move || {
         App::new()
             .route("/health_check", web::get().to(health_check))
+            .configure(|cfg| {
+                // Only when the worker runs in this process.
+                if let Some(state) = &worker_state {
+                    cfg.app_data(state.clone())
+                        .route("/worker/health_check", web::get().to(worker_health_check));
+                }
+            })
             .route("/market", web::get().to(get_coin_market_details))
+            .route("/convert", web::get().to(convert_amount))
+            .route("/coins/{id}/history", web::get().to(get_coin_history))
+            .route("/coins/{id}/sparkline", web::get().to(get_coin_sparkline))
+            .route("/coins/{id}/ohlc", web::get().to(get_coin_ohlc))
+            .route("/worker/leader", web::get().to(get_worker_leader))
+            .route("/worker/status", web::get().to(get_worker_status))
+            .route(
+                "/{chain}/token/{contract_address}/price",
+                web::get().to(get_token_price),
+            )
             // .route(
             //     "/nft/{address}",
             //     web::get().to(get_native_balance_by_wallet),
@@ -59,32 +111,104 @@ pub fn run(
             //         ),
             // )
             .app_data(db_pool.clone())
-            .app_data(gecko_client.clone())
+            .app_data(price_source.clone())
+            .app_data(live_token_prices.clone())
             .app_data(base_url.clone())
+            .app_data(defaults.clone())
+            .app_data(worker_lock_id.clone())
     })
+    // Signals are handled by `main`, which stops the API and the worker together.
+    .disable_signals()
+    .shutdown_timeout(shutdown_timeout.as_secs())
     .listen(listner)?
     .run();
     Ok(server)
 }
 
 impl Application {
-    pub async fn build(configuration: Settings) -> Result<Self, std::io::Error> {
+    /// `gecko_client` is shared with the worker of the same process, so both
+    /// draw on the same request budget and circuit breaker. The worker health check
+    /// is served when `worker_state` is given.
+    pub async fn build(
+        configuration: Settings,
+        gecko_client: Arc<GeckoClient>,
+        worker_state: Option<Arc<WorkerState>>,
+    ) -> Result<Self, std::io::Error> {
         let connection_pool = get_connection_pool(&configuration.database);
-        let timeout = configuration.gecko_client.timeout();
-        let gecko_client = GeckoClient::new(
-            configuration.gecko_client.url,
-            timeout,
-        );
+        let price_source = gecko_client;
         let address = configuration.application.url();
         let listner = TcpListener::bind(address)?;
         let port = listner.local_addr().unwrap().port();
-        let server = run(listner, connection_pool, gecko_client, configuration.application.base_url)?;
+        let currencies = configuration
+            .worker
+            .currencies()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+        let defaults = MarketDefaults {
+            currency: currencies[0],
+            source: configuration.worker.providers[0].clone(),
+        };
+        let server = run(
+            listner,
+            connection_pool.clone(),
+            LivePrices {
+                source: price_source,
+                tokens: configuration.token_price.live_prices(),
+            },
+            configuration.application.base_url.clone(),
+            defaults,
+            WorkerRoutes {
+                lock_id: configuration.worker.leader_election.lock_id,
+                state: worker_state,
+            },
+            configuration.application.shutdown_timeout(),
+        )?;
+        Ok(Self { port, server, db_pool: connection_pool })
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+    pub fn handle(&self) -> ServerHandle {
+        self.server.handle()
+    }
+    /// Serves until stopped through `handle`, then closes the database pool.
+    pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
+        self.server.await?;
+        self.db_pool.close().await;
+        Ok(())
+    }
+}
+
+/// The health endpoint of a worker-only process, bound to the application address.
+pub struct WorkerApplication {
+    port: u16,
+    server: Server,
+}
+
+impl WorkerApplication {
+    pub fn build(configuration: &Settings, state: Arc<WorkerState>) -> Result<Self, std::io::Error> {
+        let listner = TcpListener::bind(configuration.application.url())?;
+        let port = listner.local_addr().unwrap().port();
+        let state = web::Data::new(state);
+        let server = HttpServer::new(move || {
+            App::new()
+                .route("/health_check", web::get().to(worker_health_check))
+                .route("/worker/health_check", web::get().to(worker_health_check))
+                .app_data(state.clone())
+        })
+        .disable_signals()
+        .shutdown_timeout(configuration.application.shutdown_timeout().as_secs())
+        .listen(listner)?
+        .run();
         Ok(Self { port, server })
     }
 
     pub fn port(&self) -> u16 {
         self.port
     }
+    pub fn handle(&self) -> ServerHandle {
+        self.server.handle()
+    }
     pub async fn run_until_stopped(self) -> Result<(), std::io::Error> {
         self.server.await
     }
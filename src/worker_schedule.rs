@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// Prefix of the refresh tier jobs.
+pub const TIER_PREFIX: &str = "tier:";
+/// Prefix of the OHLC candle refreshes, one per interval.
+pub const OHLC_PREFIX: &str = "ohlc:";
+/// Refresh of the coin registry.
+pub const COINS_LIST_JOB: &str = "coins_list";
+/// Refresh of the BTC exchange rates.
+pub const EXCHANGE_RATES_JOB: &str = "exchange_rates";
+/// Deletion of the ingestion runs older than their retention.
+pub const INGESTION_RUNS_RETENTION_JOB: &str = "ingestion_runs_retention";
+
+/// Next due time of every scheduled job that has run at least once.
+/// Jobs missing from the result have never run and are due immediately.
+pub async fn next_due_times(
+    pool: &PgPool,
+    names: &[String],
+) -> Result<HashMap<String, DateTime<Utc>>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"SELECT name, next_due_at FROM worker_schedule WHERE name = ANY($1)"#,
+        names,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.name, row.next_due_at))
+        .collect())
+}
+
+/// Every scheduled job whose name starts with `prefix`.
+pub async fn scheduled_runs(
+    pool: &PgPool,
+    prefix: &str,
+) -> Result<Vec<(String, DateTime<Utc>)>, sqlx::Error> {
+    let rows = sqlx::query!(
+        r#"SELECT name, next_due_at FROM worker_schedule WHERE starts_with(name, $1) ORDER BY name"#,
+        prefix,
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(rows
+        .into_iter()
+        .map(|row| (row.name, row.next_due_at))
+        .collect())
+}
+
+pub async fn schedule_next_run(
+    pool: &PgPool,
+    name: &str,
+    next_due_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+            INSERT INTO worker_schedule (name, next_due_at) VALUES ($1, $2)
+            ON CONFLICT (name) DO UPDATE SET
+                next_due_at = $2,
+                updated_at = CURRENT_TIMESTAMP
+            "#,
+        name,
+        next_due_at,
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,165 @@
+use std::{collections::HashMap, time::Duration};
+
+use server::{
+    binance_client::{BinanceClient, BinanceError},
+    domains::Currency,
+    gecko_client::MarketOrder,
+    price_source::{BinanceSource, MarketPage, PriceSource, PriceSourceError},
+    resilience::CircuitBreaker,
+};
+use wiremock::{
+    matchers::{method, path, query_param},
+    Mock, MockServer, ResponseTemplate,
+};
+
+fn source(server: &MockServer) -> BinanceSource {
+    let client = BinanceClient::new(
+        server.uri(),
+        Duration::from_secs(5),
+        CircuitBreaker::new(3, Duration::from_secs(60)),
+    );
+    let assets = HashMap::from([
+        ("BTC".to_string(), "bitcoin".to_string()),
+        ("ETH".to_string(), "ethereum".to_string()),
+        ("DOGE".to_string(), "dogecoin".to_string()),
+    ]);
+    BinanceSource::new(client, assets)
+}
+
+fn page(page: u16) -> MarketPage {
+    MarketPage {
+        page,
+        per_page: 100,
+        order: MarketOrder::MarketCapDesc,
+        sparkline: false,
+    }
+}
+
+fn ticker(symbol: &str, last_price: &str) -> serde_json::Value {
+    serde_json::json!({
+        "symbol": symbol,
+        "priceChange": "-100.00",
+        "priceChangePercent": "-0.33",
+        "lastPrice": last_price,
+        "highPrice": "30500.00",
+        "lowPrice": "29500.00",
+        "quoteVolume": "123456789.5",
+        "closeTime": 1680000000000i64
+    })
+}
+
+/// DOGEUSDT is halted, so only BTC and ETH can be priced in USD.
+async fn mount_exchange_info(server: &MockServer) {
+    let symbols = serde_json::json!({
+        "symbols": [
+            { "symbol": "BTCUSDT", "status": "TRADING", "baseAsset": "BTC", "quoteAsset": "USDT" },
+            { "symbol": "ETHUSDT", "status": "TRADING", "baseAsset": "ETH", "quoteAsset": "USDT" },
+            { "symbol": "DOGEUSDT", "status": "HALT", "baseAsset": "DOGE", "quoteAsset": "USDT" }
+        ]
+    });
+    Mock::given(method("GET"))
+        .and(path("/exchangeInfo"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(symbols))
+        .mount(server)
+        .await;
+}
+
+#[tokio::test]
+async fn list_markets_prices_every_tradable_asset_on_the_first_page() {
+    let server = MockServer::start().await;
+    mount_exchange_info(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/ticker/24hr"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![
+            ticker("BTCUSDT", "30000.00"),
+            ticker("ETHUSDT", "2000.00"),
+        ]))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let source = source(&server);
+
+    let mut markets = source.list_markets(&Currency::USD, &page(1)).await.unwrap();
+
+    markets.sort_by(|a, b| a.id.cmp(&b.id));
+    let ids: Vec<_> = markets.iter().map(|m| m.id.as_deref().unwrap()).collect();
+    assert_eq!(ids, ["bitcoin", "ethereum"]);
+    assert_eq!(markets[0].symbol.as_deref(), Some("btc"));
+    assert_eq!(markets[0].current_price, Some(30000.0));
+    assert_eq!(markets[0].total_volume, Some(123456789.5));
+    assert_eq!(markets[0].price_change_percentage_24h, Some(-0.33));
+}
+
+#[tokio::test]
+async fn list_markets_has_nothing_past_the_first_page() {
+    let server = MockServer::start().await;
+    mount_exchange_info(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/ticker/24hr"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![ticker("BTCUSDT", "30000.00")]))
+        .expect(0)
+        .mount(&server)
+        .await;
+    let source = source(&server);
+
+    let markets = source.list_markets(&Currency::USD, &page(2)).await.unwrap();
+
+    assert!(markets.is_empty());
+    assert!(!source.paginates());
+}
+
+#[tokio::test]
+async fn fetch_quotes_only_asks_for_the_requested_coins() {
+    let server = MockServer::start().await;
+    mount_exchange_info(&server).await;
+    Mock::given(method("GET"))
+        .and(path("/ticker/24hr"))
+        .and(query_param("symbols", r#"["ETHUSDT"]"#))
+        .respond_with(ResponseTemplate::new(200).set_body_json(vec![ticker("ETHUSDT", "2000.00")]))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let source = source(&server);
+    let ids = ["ethereum".to_string(), "dogecoin".to_string(), "solana".to_string()];
+
+    let quotes = source.fetch_quotes(&ids, &Currency::USD).await.unwrap();
+
+    assert_eq!(quotes.len(), 1);
+    assert_eq!(quotes[0].coin_id, "ethereum");
+    assert_eq!(quotes[0].price, 2000.0);
+    assert_eq!(quotes[0].source, "binance");
+    assert_eq!(quotes[0].last_updated.timestamp_millis(), 1680000000000);
+}
+
+#[tokio::test]
+async fn unsupported_currency_is_rejected_without_a_call() {
+    let server = MockServer::start().await;
+    let source = source(&server);
+
+    let error = source.fetch_quotes(&["bitcoin".to_string()], &Currency::CHF).await.unwrap_err();
+
+    assert!(matches!(error, PriceSourceError::UnsupportedCurrency(_)));
+    assert!(server.received_requests().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn rate_limit_suspends_the_source_for_the_retry_after() {
+    for status in [429, 418] {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/exchangeInfo"))
+            .respond_with(ResponseTemplate::new(status).insert_header("Retry-After", "120"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let source = source(&server);
+
+        let result = source.list_markets(&Currency::USD, &page(1)).await;
+
+        assert!(matches!(result, Err(PriceSourceError::BinanceError(BinanceError::RateLimited(Some(_))))));
+        let suspended_for = source.suspended_for().expect("The source is not suspended");
+        assert!(suspended_for > Duration::from_secs(110) && suspended_for <= Duration::from_secs(120));
+        // Suspended calls never reach Binance.
+        assert!(source.list_markets(&Currency::USD, &page(1)).await.is_err());
+    }
+}
@@ -0,0 +1,111 @@
+use std::time::Duration;
+
+use server::{
+    gecko_client::{GeckoClient, GeckoError},
+    resilience::{Backoff, CircuitBreaker, TokenBucket},
+};
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+fn client(server: &MockServer) -> GeckoClient {
+    GeckoClient::new(
+        server.uri(),
+        Duration::from_secs(5),
+        None,
+        TokenBucket::new(100, 6000),
+        Backoff {
+            base: Duration::from_millis(1),
+            max: Duration::from_secs(2),
+            max_retries: 2,
+        },
+        CircuitBreaker::new(1, Duration::from_secs(60)),
+    )
+}
+
+fn rates() -> ResponseTemplate {
+    ResponseTemplate::new(200).set_body_json(serde_json::json!({
+        "rates": {
+            "usd": { "name": "US Dollar", "unit": "$", "value": 30000.0, "type": "fiat" }
+        }
+    }))
+}
+
+#[tokio::test]
+async fn rate_limited_request_is_retried() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/exchange_rates"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "0"))
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/exchange_rates"))
+        .respond_with(rates())
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = client(&server);
+
+    let rates = client.exchange_rates().await.expect("Retried request failed");
+
+    assert_eq!(rates.rates["usd"].value, 30000.0);
+    assert_eq!(client.suspended_for(), None);
+}
+
+#[tokio::test]
+async fn retry_after_beyond_the_backoff_cap_opens_the_circuit() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/exchange_rates"))
+        .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "600"))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = client(&server);
+
+    let error = client.exchange_rates().await.unwrap_err();
+
+    assert!(matches!(error, GeckoError::RateLimited(Some(_))));
+    assert!(client.suspended_for().is_some_and(|left| left > Duration::from_secs(590)));
+    assert!(matches!(client.exchange_rates().await, Err(GeckoError::CircuitOpen)));
+}
+
+#[tokio::test]
+async fn server_errors_open_the_circuit_once_retries_are_exhausted() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/exchange_rates"))
+        .respond_with(ResponseTemplate::new(503))
+        // The first attempt and both retries; nothing once the circuit is open.
+        .expect(3)
+        .mount(&server)
+        .await;
+    let client = client(&server);
+
+    let error = client.exchange_rates().await.unwrap_err();
+
+    assert!(matches!(error, GeckoError::ServerError(status) if status.as_u16() == 503));
+    assert!(client.suspended_for().is_some());
+    assert!(matches!(client.exchange_rates().await, Err(GeckoError::CircuitOpen)));
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried_and_keep_the_circuit_closed() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/exchange_rates"))
+        .respond_with(ResponseTemplate::new(404))
+        .expect(1)
+        .mount(&server)
+        .await;
+    let client = client(&server);
+
+    let error = client.exchange_rates().await.unwrap_err();
+
+    assert!(error.is_client_error());
+    assert_eq!(client.suspended_for(), None);
+}